@@ -0,0 +1,224 @@
+//! Renders a tiling to a PNG or SVG image without a window, for figures
+//! generated in batch, as in `cargo run-host --bin render -- 7 3 out.png`.
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
+
+#[cfg(not(target_arch = "wasm32"))]
+const USAGE: &str = "\
+usage: render [OPTIONS] P Q OUTPUT
+
+Renders the tiling {P,Q}, where P or Q may be inf for ∞, to the image file OUTPUT,
+which is drawn as vectors if it ends in .svg, and as a PNG file otherwise.
+
+options:
+    --table FILE         fragment table of the tiling, instead of deriving it
+    --depth N            depth to generate tiles up to [default: 5]
+    --projection NAME    poincare, klein, hyperboloid, half_plane, band, gans,
+                         azimuthal or equal_area [default: poincare]
+    --isometry M         camera transform as 9 comma-separated numbers in
+                         column-major order, as App.isometry() gives them
+    --size WxH           size of the image in pixels [default: 1024x1024]
+    --min-size PX        leave tiles smaller than this out of SVG images
+                         [default: 0.5]";
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    if let Err(err) = native::run() {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use std::error::Error;
+    use std::fs::File;
+    use std::io::{BufWriter, Write};
+
+    use cgmath::Vector2;
+    use hyperbolic::camera::{checked_isometry, matrix_from_slice};
+    use hyperbolic::offscreen::Offscreen;
+    use hyperbolic::pipeline::Projection;
+    use hyperbolic::svg::{self, SvgView};
+    use hyperbolic::tiling::{Order, TilingGenerator};
+
+    use super::USAGE;
+
+    struct Options {
+        p: Option<usize>,
+        q: Option<usize>,
+        output: String,
+        table: Option<String>,
+        depth: usize,
+        projection: Projection,
+        isometry: Option<Vec<f64>>,
+        size: Vector2<u32>,
+        min_size: f64,
+    }
+
+    fn parse_size(s: &str) -> Option<Vector2<u32>> {
+        let (width, height) = s.split_once('x')?;
+        let size = Vector2::new(width.parse().ok()?, height.parse().ok()?);
+        (size.x > 0 && size.y > 0).then_some(size)
+    }
+
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut table = None;
+        let mut depth = 5;
+        let mut projection = Projection::Poincare;
+        let mut isometry = None;
+        let mut size = Vector2::new(1024, 1024);
+        let mut min_size = 0.5;
+        let mut positional = Vec::new();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                positional.push(arg);
+                continue;
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} needs a value\n\n{}", arg, USAGE))?;
+            let invalid = || format!("invalid value for {}: {}\n\n{}", arg, value, USAGE);
+            match arg.as_str() {
+                "--table" => table = Some(value),
+                "--depth" => depth = value.parse().map_err(|_| invalid())?,
+                "--projection" => {
+                    projection = Projection::from_name(&value).ok_or_else(invalid)?;
+                }
+                "--isometry" => {
+                    let entries = value
+                        .split(',')
+                        .map(|x| x.trim().parse())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| invalid())?;
+                    isometry = Some(entries);
+                }
+                "--size" => size = parse_size(&value).ok_or_else(invalid)?,
+                "--min-size" => min_size = value.parse().map_err(|_| invalid())?,
+                _ => return Err(format!("unknown option {}\n\n{}", arg, USAGE)),
+            }
+        }
+        let [p, q, output]: [String; 3] = positional.try_into().map_err(|_| USAGE.to_string())?;
+        let order = |s: &str| {
+            s.parse()
+                .map(|Order(n)| n)
+                .map_err(|_| format!("invalid order: {}\n\n{}", s, USAGE))
+        };
+        Ok(Options {
+            p: order(&p)?,
+            q: order(&q)?,
+            output,
+            table,
+            depth,
+            projection,
+            isometry,
+            size,
+            min_size,
+        })
+    }
+
+    pub fn run() -> Result<(), Box<dyn Error>> {
+        env_logger::init();
+        let options = parse(std::env::args().skip(1))?;
+        let tiling = match &options.table {
+            Some(path) => {
+                let table = std::fs::read_to_string(path)
+                    .map_err(|err| format!("could not read {}: {}", path, err))?;
+                TilingGenerator::try_new(options.p, options.q, &table)?
+            }
+            None => TilingGenerator::try_from_schlafli(options.p, options.q)?,
+        };
+
+        let isometry = options
+            .isometry
+            .as_deref()
+            .map(|entries| matrix_from_slice(entries).and_then(checked_isometry))
+            .transpose()?;
+        let create = || {
+            File::create(&options.output)
+                .map_err(|err| format!("could not create {}: {}", options.output, err))
+        };
+
+        if options.output.ends_with(".svg") {
+            let mut view = SvgView::new(options.projection, options.size.cast().unwrap());
+            if let Some(m) = isometry {
+                view.isometry = m;
+            }
+            view.min_size = options.min_size;
+            let image = svg::export(&tiling, hyperbolic::COLORS, options.depth, &view)?;
+            create()?.write_all(image.as_bytes())?;
+            return Ok(());
+        }
+
+        let mut offscreen = pollster::block_on(Offscreen::new(options.size))?;
+        offscreen.set_tiling(&tiling, options.depth);
+        offscreen.set_projection(options.projection);
+        if let Some(m) = isometry {
+            offscreen.set_isometry(m)?;
+        }
+        offscreen.write_png(BufWriter::new(create()?))?;
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn args(line: &str) -> impl Iterator<Item = String> + '_ {
+            line.split_whitespace().map(String::from)
+        }
+
+        #[test]
+        fn defaults() {
+            let options = parse(args("7 3 out.png")).unwrap();
+            assert_eq!((options.p, options.q), (Some(7), Some(3)));
+            assert_eq!(options.output, "out.png");
+            assert_eq!(options.table, None);
+            assert_eq!(options.depth, 5);
+            assert_eq!(options.projection, Projection::Poincare);
+            assert_eq!(options.isometry, None);
+            assert_eq!(options.size, Vector2::new(1024, 1024));
+            assert_eq!(options.min_size, 0.5);
+        }
+
+        #[test]
+        fn options() {
+            let options = parse(args(
+                "--depth 3 5 --projection half_plane inf --size 640x480 \
+                 --isometry 1,0,0,0,1,0,0,0,1 --min-size 2 out.svg --table t.txt",
+            ))
+            .unwrap();
+            assert_eq!((options.p, options.q), (Some(5), None));
+            assert_eq!(options.output, "out.svg");
+            assert_eq!(options.table.as_deref(), Some("t.txt"));
+            assert_eq!(options.depth, 3);
+            assert_eq!(options.projection, Projection::HalfPlane);
+            assert_eq!(
+                options.isometry,
+                Some(vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0])
+            );
+            assert_eq!(options.size, Vector2::new(640, 480));
+            assert_eq!(options.min_size, 2.0);
+        }
+
+        #[test]
+        fn invalid() {
+            for line in [
+                "",
+                "7 3",
+                "7 3 a.png b.png",
+                "x 3 out.png",
+                "7 3 out.png --depth",
+                "7 3 out.png --depth -1",
+                "7 3 out.png --projection mercator",
+                "7 3 out.png --isometry 1,0,x",
+                "7 3 out.png --size 640",
+                "7 3 out.png --size 0x480",
+                "7 3 out.png --colour red",
+            ] {
+                assert!(parse(args(line)).is_err(), "{:?} was accepted", line);
+            }
+        }
+    }
+}
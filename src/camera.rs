@@ -1,10 +1,66 @@
+use std::fmt;
 use std::ops::Deref;
 
 use bytemuck::{Pod, Zeroable};
-use cgmath::{Matrix4, One, Vector2};
+use cgmath::{
+    perspective, InnerSpace, Matrix2, Matrix3, Matrix4, One, Point3, Rad, SquareMatrix, Vector2,
+    Vector3, VectorSpace,
+};
 use wgpu::{util::DeviceExt, Device, Queue};
 
-use crate::{translation, window::Window, Surface};
+use crate::{
+    lorentz_inverse, lorentz_orthonormalize,
+    pipeline::{unproject_pole, Projection},
+    tiling::{minkowski, Neighbours},
+    translation,
+    window::Window,
+    Surface,
+};
+
+/// Why a matrix cannot be used as the camera's isometry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IsometryError {
+    /// The matrix was given with this many entries rather than 9.
+    Shape(usize),
+    /// The matrix doesn't preserve the Minkowski form, swaps the sheets of
+    /// the hyperboloid, or reflects the plane.
+    NotIsometry,
+}
+impl fmt::Display for IsometryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IsometryError::Shape(len) => {
+                write!(f, "an isometry has 9 entries, but {} were given", len)
+            }
+            IsometryError::NotIsometry => {
+                write!(f, "the matrix is not an isometry of the hyperbolic plane")
+            }
+        }
+    }
+}
+impl std::error::Error for IsometryError {}
+
+/// Matrix of the nine `entries` in column-major order, as
+/// [`crate::App::isometry`] gives them.
+pub fn matrix_from_slice(entries: &[f64]) -> Result<Matrix3<f64>, IsometryError> {
+    match entries {
+        &[a, b, c, d, e, f, g, h, i] => Ok([[a, b, c], [d, e, f], [g, h, i]].into()),
+        _ => Err(IsometryError::Shape(entries.len())),
+    }
+}
+
+/// An isometry close to `m`, if `m` preserves the Minkowski form, the upper
+/// sheet and the orientation of the plane up to rounding. Reflections are
+/// turned down as the camera only ever moves by rotations and translations,
+/// and the turns of a mirrored view would go the wrong way.
+pub fn checked_isometry(m: Matrix3<f64>) -> Result<Matrix3<f64>, IsometryError> {
+    let error: [[f64; 3]; 3] = (lorentz_inverse(m) * m - Matrix3::one()).into();
+    let preserves_form = error.iter().flatten().all(|e| e.abs() < ISOMETRY_TOLERANCE);
+    if m.z.z <= 0.0 || m.determinant() < 0.0 || !preserves_form {
+        return Err(IsometryError::NotIsometry);
+    }
+    Ok(lorentz_orthonormalize(m))
+}
 
 pub struct Camera {
     pub bind_group: CameraBindGroup,
@@ -27,38 +83,313 @@ impl Camera {
         self.bind_group.update(queue, &self.tracker);
     }
 
+    /// Moves through the plane with the pointer at `pos` at the time `now`,
+    /// stopping any motion the camera was making by itself.
     pub fn update_delta<W: Window>(
         &mut self,
         queue: &Queue,
         surface: &Surface<W>,
         pos: Vector2<f64>,
+        now: f64,
+    ) {
+        self.tracker.motion = None;
+        if let Some(delta) = self.controller.update(pos) {
+            let delta = delta * 2.0 / surface.size().y;
+            self.controller.track(delta, now);
+            self.tracker.translate(delta);
+            self.bind_group.update(queue, &self.tracker);
+            surface.window.request_redraw();
+        }
+    }
+
+    /// Moves the view through the plane towards `offset` on screen, with y
+    /// pointing up, by its length in screen units, stopping any motion the
+    /// camera was making by itself.
+    pub fn move_by(&mut self, queue: &Queue, offset: Vector2<f64>) {
+        self.tracker.motion = None;
+        // The plane moves the other way across the screen.
+        self.tracker.translate(-offset);
+        self.bind_group.update(queue, &self.tracker);
+    }
+
+    /// Like [`Camera::update_delta`], but pans the Euclidean view of the
+    /// projected plane instead of moving through the hyperbolic plane.
+    pub fn update_view_delta<W: Window>(
+        &mut self,
+        queue: &Queue,
+        surface: &Surface<W>,
+        pos: Vector2<f64>,
     ) {
         if let Some(delta) = self.controller.update(pos) {
-            self.tracker.translate(delta * 2.0 / surface.size().y);
+            self.tracker.pan_view(delta * 2.0 / surface.size().y);
             self.bind_group.update(queue, &self.tracker);
             surface.window.request_redraw();
         }
     }
 
+    /// Like [`Camera::update_delta`], but orbits the 3D view of the
+    /// hyperboloid.
+    pub fn update_orbit_delta<W: Window>(
+        &mut self,
+        queue: &Queue,
+        surface: &Surface<W>,
+        pos: Vector2<f64>,
+    ) {
+        if let Some(delta) = self.controller.update(pos) {
+            self.tracker.orbit.turn(delta * 2.0 / surface.size().y);
+            self.bind_group.update(queue, &self.tracker);
+            surface.window.request_redraw();
+        }
+    }
+
+    /// Like [`Camera::update_delta`], but turns the view about the centre of
+    /// the screen by the angle the pointer swept around it.
+    pub fn update_rotation_delta<W: Window>(
+        &mut self,
+        queue: &Queue,
+        surface: &Surface<W>,
+        pos: Vector2<f64>,
+    ) {
+        if let Some(delta) = self.controller.update(pos) {
+            let size = surface.size();
+            let centre = Vector2::new(size.x, -size.y) / 2.0;
+            let (from, to) = (pos - delta - centre, pos - centre);
+            let angle = (from.x * to.y - from.y * to.x).atan2(from.dot(to));
+            self.rotate_by(queue, angle);
+            surface.window.request_redraw();
+        }
+    }
+
     pub fn reset_delta(&mut self) {
         self.controller.reset();
     }
+
+    /// Ends a drag at the time `now`, letting the camera glide on if it was
+    /// still moving through the plane, to be advanced by [`Camera::animate`].
+    /// Returns whether it glides.
+    pub fn release(&mut self, now: f64) -> bool {
+        let velocity = self.controller.velocity(now);
+        self.controller.reset();
+        if velocity.magnitude() < MIN_GLIDE_SPEED {
+            return false;
+        }
+        self.tracker.motion = Some(Motion::Glide {
+            velocity,
+            time: now,
+        });
+        true
+    }
+
+    /// Sets the symmetries of the tiling used to keep the camera over the
+    /// tile around the origin, or stops doing so if `None`.
+    pub fn set_neighbours(&mut self, queue: &Queue, neighbours: Option<Neighbours>) {
+        self.tracker.neighbours = neighbours;
+        self.tracker.recentre();
+        self.bind_group.update(queue, &self.tracker);
+    }
+
+    /// Sets how quickly gliding slows down, as the rate at which the speed
+    /// decays per second.
+    pub fn set_friction(&mut self, friction: f64) {
+        self.tracker.friction = friction.max(0.0);
+    }
+
+    /// Starts flying along the geodesic that brings `target`, a point of the
+    /// hyperboloid, to the centre of the view over `duration` milliseconds
+    /// from the time `now`, to be advanced by [`Camera::animate`].
+    pub fn fly_to(&mut self, queue: &Queue, target: Vector3<f64>, now: f64, duration: f64) {
+        self.tracker.fly_to(target, now, duration);
+        self.bind_group.update(queue, &self.tracker);
+    }
+
+    /// Turns the view counterclockwise about the centre of the screen by
+    /// `angle` radians.
+    pub fn rotate_by(&mut self, queue: &Queue, angle: f64) {
+        self.tracker.rotate(angle);
+        self.bind_group.update(queue, &self.tracker);
+    }
+
+    /// Turns the view about the centre of the screen so that the axes of the
+    /// plane, carried to the centre, are turned by `angle` radians.
+    pub fn set_rotation(&mut self, queue: &Queue, angle: f64) {
+        self.tracker.rotate(angle - self.tracker.rotation());
+        self.bind_group.update(queue, &self.tracker);
+    }
+
+    pub fn isometry(&self) -> Matrix3<f64> {
+        self.tracker.isometry()
+    }
+
+    /// Returns the product of the symmetries the camera was moved back by
+    /// since this was last called. A point of the plane seen before then is
+    /// now seen at its image under the inverse.
+    pub fn take_recentred(&mut self) -> Matrix3<f64> {
+        std::mem::replace(&mut self.tracker.recentred, Matrix3::one())
+    }
+
+    /// Point of the plane, before the camera's transform, seen at the pixel
+    /// `pos` measured from the top left corner, if any.
+    pub fn unproject<W: Window>(
+        &self,
+        surface: &Surface<W>,
+        pos: Vector2<f64>,
+    ) -> Option<Vector3<f64>> {
+        let size = surface.size();
+        let screen = Vector2::new(2.0 * pos.x / size.x - 1.0, 1.0 - 2.0 * pos.y / size.y);
+        self.tracker.unproject(screen)
+    }
+
+    /// Moves the camera to the isometry `m`, taking the plane to the view,
+    /// which must preserve the Minkowski form and the upper sheet.
+    pub fn try_set_isometry(
+        &mut self,
+        queue: &Queue,
+        m: Matrix3<f64>,
+    ) -> Result<(), IsometryError> {
+        self.tracker.set_isometry(m)?;
+        self.bind_group.update(queue, &self.tracker);
+        Ok(())
+    }
+
+    /// Projection the camera shows, or is blending into.
+    pub fn projection(&self) -> Projection {
+        self.tracker.projection
+    }
+
+    /// Point of the projected plane at the centre of the screen, and the
+    /// scale of the Euclidean view.
+    pub fn view(&self) -> (Vector2<f64>, f64) {
+        (self.tracker.view_centre, self.tracker.view_scale)
+    }
+
+    /// Whether the hyperboloid is shown in 3D, and not blended with another
+    /// projection.
+    pub fn is_3d(&self) -> bool {
+        self.tracker.projection == Projection::Hyperboloid && self.tracker.transition.is_none()
+    }
+
+    /// Scales the Euclidean view by `factor` about the pixel at `pos`,
+    /// measured from the top left corner, or moves the 3D view closer by
+    /// `factor`.
+    pub fn zoom_view<W: Window>(
+        &mut self,
+        queue: &Queue,
+        surface: &Surface<W>,
+        factor: f64,
+        pos: Vector2<f64>,
+    ) {
+        let size = surface.size();
+        let at = Vector2::new(pos.x - size.x / 2.0, size.y / 2.0 - pos.y) * 2.0 / size.y;
+        if self.tracker.projection == Projection::Hyperboloid {
+            self.tracker.orbit.approach(factor);
+        } else {
+            self.tracker.zoom_view(factor, at);
+        }
+        self.bind_group.update(queue, &self.tracker);
+        surface.window.request_redraw();
+    }
+
+    /// Sets the scale of the Euclidean view to `zoom` times the one suiting
+    /// the projection, keeping the centre of the screen in place.
+    pub fn set_zoom(&mut self, queue: &Queue, zoom: f64) {
+        self.tracker.set_zoom(zoom);
+        self.bind_group.update(queue, &self.tracker);
+    }
+
+    /// Scale of the Euclidean view relative to the one suiting the
+    /// projection.
+    pub fn zoom(&self) -> f64 {
+        self.tracker.zoom()
+    }
+
+    /// Switches to `projection` at once, resetting the Euclidean view to the
+    /// one suiting it.
+    pub fn set_projection(&mut self, queue: &Queue, projection: Projection) {
+        self.tracker.set_projection(projection);
+        self.bind_group.update(queue, &self.tracker);
+    }
+
+    /// Starts blending into `projection` over `duration` milliseconds from
+    /// the time `now`, to be advanced by [`Camera::animate`].
+    pub fn animate_projection(
+        &mut self,
+        queue: &Queue,
+        projection: Projection,
+        now: f64,
+        duration: f64,
+    ) {
+        self.tracker.animate_projection(projection, now, duration);
+        self.bind_group.update(queue, &self.tracker);
+    }
+
+    /// Advances the animations to the time `now`, returning whether they
+    /// need another frame.
+    pub fn animate(&mut self, queue: &Queue, now: f64) -> bool {
+        let animating = self.tracker.animate(now);
+        if animating {
+            self.bind_group.update(queue, &self.tracker);
+        }
+        animating
+    }
 }
 
+/// Longest pause before releasing a drag after which the camera doesn't glide,
+/// in milliseconds.
+const MAX_GLIDE_PAUSE: f64 = 80.0;
+
+/// Slowest glide, in screen units per millisecond, below which the camera
+/// stops.
+const MIN_GLIDE_SPEED: f64 = 1e-5;
+
+/// Longest step of a glide, in milliseconds, so that a late frame doesn't
+/// throw the camera far off.
+const MAX_GLIDE_STEP: f64 = 100.0;
+
+/// Rate at which the speed of a glide decays per second, unless set.
+const DEFAULT_FRICTION: f64 = 4.0;
+
 pub struct CameraController {
     value: Option<Vector2<f64>>,
+    /// Smoothed velocity of the drag through the plane, in screen units per
+    /// millisecond, and the time it was last moved.
+    velocity: Vector2<f64>,
+    last: Option<f64>,
 }
 impl CameraController {
     pub fn new() -> Self {
-        CameraController { value: None }
+        CameraController {
+            value: None,
+            velocity: Vector2::new(0.0, 0.0),
+            last: None,
+        }
     }
 
     pub fn update(&mut self, pos: Vector2<f64>) -> Option<Vector2<f64>> {
         self.value.replace(pos).map(|old| pos - old)
     }
 
+    /// Records a move by `delta` at the time `now` into the velocity.
+    pub fn track(&mut self, delta: Vector2<f64>, now: f64) {
+        if let Some(last) = self.last.filter(|&last| now > last) {
+            // Average over the last few moves, as pointer events are uneven.
+            self.velocity = self.velocity.lerp(delta / (now - last), 0.5);
+        }
+        self.last = Some(now);
+    }
+
+    /// Velocity of the drag at the time `now`, which is zero if it paused
+    /// before then.
+    pub fn velocity(&self, now: f64) -> Vector2<f64> {
+        match self.last {
+            Some(last) if now - last <= MAX_GLIDE_PAUSE => self.velocity,
+            _ => Vector2::new(0.0, 0.0),
+        }
+    }
+
     pub fn reset(&mut self) {
         self.value = None;
+        self.velocity = Vector2::new(0.0, 0.0);
+        self.last = None;
     }
 }
 impl Default for CameraController {
@@ -67,9 +398,155 @@ impl Default for CameraController {
     }
 }
 
+/// Blend from one projection into the current one.
+struct Transition {
+    previous: Projection,
+    /// Time the blend started at and how long it lasts, in milliseconds.
+    start: f64,
+    duration: f64,
+    /// Centre and scale of the Euclidean view when the blend started.
+    view: (Vector2<f64>, f64),
+}
+
+/// Movement of the camera through the plane that carries on by itself.
+enum Motion {
+    /// Coasting on after a drag at `velocity` in screen units per
+    /// millisecond, as of the time `time`, slowing down by friction.
+    Glide { velocity: Vector2<f64>, time: f64 },
+    /// Flying `distance` along the geodesic leaving the centre of the view in
+    /// `direction`, easing in and out, of which `travelled` is behind.
+    Flight {
+        direction: Vector2<f64>,
+        distance: f64,
+        travelled: f64,
+        start_time: f64,
+        duration: f64,
+    },
+}
+
+/// Eases the fraction `t` of an animation in and out, so that neither end is
+/// left abruptly.
+fn ease(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Fraction of an animation lasting `duration` from `start` done at `now`.
+fn progress(start: f64, duration: f64, now: f64) -> f64 {
+    if duration > 0.0 {
+        ((now - start) / duration).clamp(0.0, 1.0)
+    } else {
+        1.0
+    }
+}
+
+/// Converts OpenGL clip coordinates, with depths from -1 to 1, into those of
+/// wgpu, with depths from 0 to 1.
+#[rustfmt::skip]
+const OPENGL_TO_WGPU: Matrix4<f64> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+/// Perspective camera orbiting the hyperboloid, with z pointing up.
+pub struct Orbit {
+    /// Angle around the z axis and above the xy plane.
+    yaw: f64,
+    pitch: f64,
+    distance: f64,
+}
+impl Orbit {
+    /// Point the camera looks at, between the pole of the Poincaré disk and
+    /// the vertex of the hyperboloid.
+    const TARGET: Point3<f64> = Point3::new(0.0, 0.0, 0.5);
+    const FOVY: f64 = std::f64::consts::FRAC_PI_4;
+    const MIN_DISTANCE: f64 = 2.0;
+    const MAX_DISTANCE: f64 = 50.0;
+    const MAX_PITCH: f64 = 1.5;
+
+    fn new() -> Self {
+        Orbit {
+            yaw: -std::f64::consts::FRAC_PI_2,
+            pitch: 0.4,
+            distance: 8.0,
+        }
+    }
+
+    /// Turns the camera by `delta` in screen units, where the height of the
+    /// screen is 2.
+    pub fn turn(&mut self, delta: Vector2<f64>) {
+        self.yaw -= delta.x;
+        self.pitch = (self.pitch - delta.y).clamp(-Self::MAX_PITCH, Self::MAX_PITCH);
+    }
+
+    /// Moves the camera `factor` times closer to its target.
+    pub fn approach(&mut self, factor: f64) {
+        self.distance = (self.distance / factor).clamp(Self::MIN_DISTANCE, Self::MAX_DISTANCE);
+    }
+
+    fn matrix(&self, aspect: f64) -> Matrix4<f64> {
+        let (sy, cy) = self.yaw.sin_cos();
+        let (sp, cp) = self.pitch.sin_cos();
+        let eye = Self::TARGET + Vector3::new(cp * cy, cp * sy, sp) * self.distance;
+        let view = Matrix4::look_at_rh(eye, Self::TARGET, Vector3::unit_z());
+        OPENGL_TO_WGPU * perspective(Rad(Self::FOVY), aspect, 0.1, 100.0) * view
+    }
+}
+
+/// Largest error in J Mᵀ J M = I allowed of an isometry M given by the user.
+const ISOMETRY_TOLERANCE: f64 = 1e-6;
+
+/// Number of moves composed into the camera's transform between corrections
+/// of its rounding errors.
+///
+/// Each move adds an error of a few ulps, and each symmetry the camera is
+/// moved back by magnifies it by up to the square of its entries, so left
+/// alone the error grows exponentially with the distance travelled. Moves
+/// are much shorter than a tile, so 64 of them cross only a few tiles and
+/// the error stays below 1e-12, while the correction, which costs a few
+/// moves' worth of arithmetic, runs rarely.
+const MOVES_PER_CORRECTION: u32 = 64;
+
+/// Range of the scale of the Euclidean view relative to the one suiting the
+/// projection, keeping repeated zooms from running the scale to 0 or ∞.
+const MIN_ZOOM: f64 = 1e-3;
+const MAX_ZOOM: f64 = 1e6;
+
 pub struct CameraTracker {
     viewport: Matrix4<f64>,
+    aspect: f64,
+    /// Point of the projected plane at the centre of the screen.
+    view_centre: Vector2<f64>,
+    /// Screen heights per unit of the projected plane, halved.
+    view_scale: f64,
+    /// Whether the screen is turned to keep the image of the origin on its x
+    /// axis, so that the band model runs along the way the camera moved.
+    along_translation: bool,
+    /// Angle the screen is turned by, if it is.
+    turn: f64,
+    projection: Projection,
+    transition: Option<Transition>,
+    /// How far the transition has gone, from 0 to 1.
+    blend: f64,
+    motion: Option<Motion>,
+    /// Rate at which the speed of a glide decays per second.
+    friction: f64,
+    /// Symmetries of the tiling moving the camera back over the tile around
+    /// the origin, if it is kept there.
+    neighbours: Option<Neighbours>,
+    /// Product of the symmetries the camera was moved back by since it was
+    /// last taken by [`Camera::take_recentred`].
+    recentred: Matrix3<f64>,
+    /// Point of the plane, in Klein coordinates, that was at the origin
+    /// before the camera was moved back by symmetries, which the screen is
+    /// turned towards for the band model.
+    anchor: Vector3<f64>,
+    pub orbit: Orbit,
     pub transform: Matrix4<f64>,
+    /// Moves composed into the transform since its rounding errors were last
+    /// corrected.
+    moves: u32,
 }
 impl CameraTracker {
     #[rustfmt::skip]
@@ -85,16 +562,328 @@ impl CameraTracker {
     pub fn new(aspect: f64) -> Self {
         CameraTracker {
             viewport: Self::ortho(aspect),
+            aspect,
+            view_centre: Vector2::new(0.0, 0.0),
+            view_scale: 1.0,
+            along_translation: false,
+            turn: 0.0,
+            projection: Projection::Poincare,
+            transition: None,
+            blend: 1.0,
+            motion: None,
+            friction: DEFAULT_FRICTION,
+            neighbours: None,
+            recentred: Matrix3::one(),
+            anchor: Vector3::unit_z(),
+            orbit: Orbit::new(),
             transform: Matrix4::one(),
+            moves: 0,
         }
     }
 
     pub fn update_viewport(&mut self, aspect: f64) {
-        self.viewport = Self::ortho(aspect);
+        self.aspect = aspect;
+        self.viewport = Self::ortho(aspect)
+            * Matrix4::from_nonuniform_scale(self.view_scale, self.view_scale, 1.0)
+            * Matrix4::from_translation(Vector3::new(
+                -self.view_centre.x,
+                -self.view_centre.y,
+                0.0,
+            ));
     }
 
+    /// Moves the Euclidean view by `delta` in screen units, where the height
+    /// of the screen is 2.
+    pub fn pan_view(&mut self, delta: Vector2<f64>) {
+        self.view_centre -= delta / self.view_scale;
+        self.update_viewport(self.aspect);
+    }
+
+    /// Scales the Euclidean view by `factor`, keeping the point at `at` in
+    /// screen units in place. Factors that aren't positive are ignored, and
+    /// the zoom stays between [`MIN_ZOOM`] and [`MAX_ZOOM`].
+    pub fn zoom_view(&mut self, factor: f64, at: Vector2<f64>) {
+        if !factor.is_finite() || factor <= 0.0 {
+            return;
+        }
+        let fixed = self.view_centre + at / self.view_scale;
+        self.view_scale = self.clamp_scale(self.view_scale * factor);
+        self.view_centre = fixed - at / self.view_scale;
+        self.update_viewport(self.aspect);
+    }
+
+    pub fn set_zoom(&mut self, zoom: f64) {
+        self.view_scale = self.clamp_scale(self.projection.home().1 * zoom);
+        self.update_viewport(self.aspect);
+    }
+
+    fn clamp_scale(&self, scale: f64) -> f64 {
+        let home = self.projection.home().1;
+        scale.clamp(home * MIN_ZOOM, home * MAX_ZOOM)
+    }
+
+    pub fn zoom(&self) -> f64 {
+        self.view_scale / self.projection.home().1
+    }
+
+    pub fn reset_view(&mut self, centre: Vector2<f64>, scale: f64) {
+        self.view_centre = centre;
+        self.view_scale = scale;
+        self.update_viewport(self.aspect);
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+        self.transition = None;
+        self.blend = 1.0;
+        let (centre, scale) = projection.home();
+        self.reset_view(centre, scale);
+        self.along_translation = projection == Projection::Band;
+        self.update_turn();
+    }
+
+    pub fn animate_projection(&mut self, projection: Projection, now: f64, duration: f64) {
+        self.transition = Some(Transition {
+            previous: self.projection,
+            start: now,
+            duration,
+            view: (self.view_centre, self.view_scale),
+        });
+        self.projection = projection;
+        self.blend = 0.0;
+        self.animate(now);
+    }
+
+    /// Advances the transition and the motion to the time `now`, returning
+    /// whether either has moved on.
+    pub fn animate(&mut self, now: f64) -> bool {
+        let transition = self.animate_transition(now);
+        let motion = self.animate_motion(now);
+        transition || motion
+    }
+
+    fn animate_transition(&mut self, now: f64) -> bool {
+        let Some(transition) = &self.transition else {
+            return false;
+        };
+        let t = progress(transition.start, transition.duration, now);
+        self.blend = ease(t);
+        let (centre, scale) = self.projection.home();
+        let (start_centre, start_scale) = transition.view;
+        self.view_centre = start_centre.lerp(centre, self.blend);
+        self.view_scale = start_scale * (scale / start_scale).powf(self.blend);
+        // The band model keeps turning the screen while it is on show.
+        self.along_translation = self.projection == Projection::Band
+            || (transition.previous == Projection::Band && t < 1.0);
+        if t >= 1.0 {
+            self.transition = None;
+            self.blend = 1.0;
+        }
+        self.update_viewport(self.aspect);
+        self.update_turn();
+        true
+    }
+
+    fn animate_motion(&mut self, now: f64) -> bool {
+        match self.motion {
+            Some(Motion::Glide { velocity, time }) => {
+                let dt = (now - time).clamp(0.0, MAX_GLIDE_STEP);
+                self.translate(velocity * dt);
+                let velocity = velocity * (-self.friction * dt / 1000.0).exp();
+                self.motion = (velocity.magnitude() >= MIN_GLIDE_SPEED).then_some(Motion::Glide {
+                    velocity,
+                    time: now,
+                });
+            }
+            Some(Motion::Flight {
+                direction,
+                distance,
+                travelled,
+                start_time,
+                duration,
+            }) => {
+                let t = progress(start_time, duration, now);
+                let along = ease(t) * distance;
+                // Translations along the same geodesic add up, so the camera
+                // is moved on by the rest of the way since the last frame.
+                self.compose(translation(-direction * (along - travelled).sinh()));
+                self.recentre();
+                self.motion = (t < 1.0).then_some(Motion::Flight {
+                    direction,
+                    distance,
+                    travelled: along,
+                    start_time,
+                    duration,
+                });
+            }
+            None => return false,
+        }
+        true
+    }
+
+    /// Starts flying along the geodesic bringing `target` to the centre.
+    pub fn fly_to(&mut self, target: Vector3<f64>, now: f64, duration: f64) {
+        let offset = (self.isometry() * target).truncate();
+        let sinh = offset.magnitude();
+        if sinh < 1e-12 {
+            self.motion = None;
+            return;
+        }
+        self.motion = Some(Motion::Flight {
+            direction: offset / sinh,
+            distance: sinh.asinh(),
+            travelled: 0.0,
+            start_time: now,
+            duration,
+        });
+        self.animate_motion(now);
+    }
+
+    /// Translates the camera by `delta` as seen on screen.
     pub fn translate(&mut self, delta: Vector2<f64>) {
-        self.transform = Matrix4::from(translation(delta)) * self.transform;
+        let delta = Matrix2::from_angle(Rad(self.turn)) * delta;
+        self.compose(translation(delta));
+        self.recentre();
+    }
+
+    /// Moves the camera back over the tile around the origin by symmetries
+    /// of the tiling, which leave the view as it is, returning their
+    /// product.
+    fn recentre(&mut self) -> Matrix3<f64> {
+        let Some(neighbours) = &self.neighbours else {
+            return Matrix3::one();
+        };
+        let mut isometry = self.isometry();
+        let mut product = Matrix3::one();
+        // Each step brings the centre of the view strictly nearer.
+        while let Some(g) = neighbours.nearest(lorentz_inverse(isometry).z) {
+            isometry = isometry * g;
+            product = product * g;
+        }
+        if product != Matrix3::one() {
+            self.recentred = self.recentred * product;
+            let anchor = lorentz_inverse(product) * self.anchor;
+            self.anchor = anchor / anchor.z;
+            self.transform = Matrix4::from(isometry);
+            self.update_turn();
+        }
+        product
+    }
+
+    /// Turns the view counterclockwise about the centre by `angle`.
+    pub fn rotate(&mut self, angle: f64) {
+        self.compose(Matrix3::from_angle_z(Rad(angle)));
+    }
+
+    /// Applies the isometry `m` after the transform, every so often making
+    /// the product an isometry again despite rounding.
+    fn compose(&mut self, m: Matrix3<f64>) {
+        let mut isometry = m * self.isometry();
+        self.moves += 1;
+        if self.moves >= MOVES_PER_CORRECTION {
+            isometry = lorentz_orthonormalize(isometry);
+            self.moves = 0;
+        }
+        self.transform = Matrix4::from(isometry);
+        self.update_turn();
+    }
+
+    /// Angle the axes of the plane are turned by once the translation taking
+    /// the origin to its image is undone.
+    pub fn rotation(&self) -> f64 {
+        let isometry = self.isometry();
+        let rotation = translation(-isometry.z.truncate()) * isometry;
+        rotation.x.y.atan2(rotation.x.x)
+    }
+
+    pub fn isometry(&self) -> Matrix3<f64> {
+        let t = self.transform;
+        Matrix3::from_cols(t.x.truncate(), t.y.truncate(), t.z.truncate())
+    }
+
+    pub fn set_isometry(&mut self, m: Matrix3<f64>) -> Result<(), IsometryError> {
+        self.transform = Matrix4::from(checked_isometry(m)?);
+        self.moves = 0;
+        self.anchor = Vector3::unit_z();
+        self.recentre();
+        self.update_turn();
+        Ok(())
+    }
+
+    /// Turns the screen towards the image of the origin, keeping the last
+    /// turn while the origin is at the centre.
+    fn update_turn(&mut self) {
+        if !self.along_translation {
+            self.turn = 0.0;
+            return;
+        }
+        let origin = self.isometry() * self.anchor;
+        if origin.x.hypot(origin.y) > 1e-9 {
+            self.turn = origin.y.atan2(origin.x);
+        }
+    }
+
+    /// Undoes the viewport, giving the point in the plane of the projection.
+    fn unview(&self, screen: Vector2<f64>) -> Vector2<f64> {
+        let v = self.viewport;
+        Vector2::new((screen.x - v.w.x) / v.x.x, (screen.y - v.w.y) / v.y.y)
+    }
+
+    /// Point of the hyperboloid seen at `screen` in the 3D view, as in
+    /// `raycast` in `projection.wgsl`.
+    fn raycast(&self, screen: Vector2<f64>) -> Option<Vector3<f64>> {
+        let inverse = self.orbit.matrix(self.aspect).invert()?;
+        let near = inverse * screen.extend(0.0).extend(1.0);
+        let far = inverse * screen.extend(1.0).extend(1.0);
+        let o = near.truncate() / near.w;
+        let d = far.truncate() / far.w - o;
+        let a = minkowski(d, d);
+        let b = minkowski(o, d);
+        let c = minkowski(o, o) + 1.0;
+        let disc = b * b - a * c;
+        if disc < 0.0 {
+            return None;
+        }
+        let roots = [(-b - disc.sqrt()) / a, (-b + disc.sqrt()) / a];
+        let (t0, t1) = (roots[0].min(roots[1]), roots[0].max(roots[1]));
+        let t = if t0 < 0.0 || o.z + t0 * d.z <= 0.0 {
+            t1
+        } else {
+            t0
+        };
+        let v = o + d * t;
+        ((0.0..=1.0).contains(&t) && v.z > 0.0).then_some(v)
+    }
+
+    /// Point of the plane, before the camera's transform, seen at `screen`
+    /// in clip coordinates, or `None` outside the image. Like the per-pixel
+    /// renderer, blends that can't be inverted use the nearer projection.
+    pub fn unproject(&self, screen: Vector2<f64>) -> Option<Vector3<f64>> {
+        let previous = self
+            .transition
+            .as_ref()
+            .map_or(self.projection, |t| t.previous);
+        let v = match (previous.pole(), self.projection.pole()) {
+            (Some(a), Some(b)) => unproject_pole(a + (b - a) * self.blend, self.unview(screen)),
+            _ => {
+                let projection = if self.blend < 0.5 {
+                    previous
+                } else {
+                    self.projection
+                };
+                match projection {
+                    Projection::Hyperboloid => self.raycast(screen),
+                    _ => projection.unproject(self.unview(screen)),
+                }
+            }
+        }?;
+        let view = Matrix3::from_angle_z(Rad(-self.turn)) * self.isometry();
+        Some(lorentz_inverse(view) * v)
+    }
+
+    /// Transform from the hyperbolic plane to the screen's frame.
+    pub fn screen_transform(&self) -> Matrix4<f64> {
+        Matrix4::from_angle_z(Rad(-self.turn)) * self.transform
     }
 }
 
@@ -103,12 +892,29 @@ impl CameraTracker {
 pub struct CameraUniform {
     viewport: [f32; 16],
     transform: [f32; 16],
+    perspective: [f32; 16],
+    inverse_perspective: [f32; 16],
+    previous: u32,
+    current: u32,
+    blend: f32,
+    _padding: u32,
 }
 impl CameraUniform {
     pub fn new(camera: &CameraTracker) -> Self {
+        let previous = camera.transition.as_ref().map(|t| t.previous);
+        let perspective = camera.orbit.matrix(camera.aspect);
+        let inverse_perspective = perspective
+            .invert()
+            .expect("the perspective projection is invertible");
         CameraUniform {
             viewport: *camera.viewport.cast().unwrap().as_ref(),
-            transform: *camera.transform.cast().unwrap().as_ref(),
+            transform: *camera.screen_transform().cast().unwrap().as_ref(),
+            perspective: *perspective.cast().unwrap().as_ref(),
+            inverse_perspective: *inverse_perspective.cast().unwrap().as_ref(),
+            previous: previous.unwrap_or(camera.projection) as u32,
+            current: camera.projection as u32,
+            blend: camera.blend as f32,
+            _padding: 0,
         }
     }
 }
@@ -123,7 +929,7 @@ impl CameraBindGroupLayout {
                 label: Some("camera bind_group_layout"),
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -181,3 +987,210 @@ impl Deref for CameraBindGroup {
         &self.bind_group
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::TAU;
+
+    use super::*;
+    use crate::pipeline::project_pole;
+    use crate::tiling::{Tiling, TilingGenerator};
+
+    /// Largest entry of J Mᵀ J M - I.
+    fn isometry_error(m: Matrix3<f64>) -> f64 {
+        let error: [[f64; 3]; 3] = (lorentz_inverse(m) * m - Matrix3::one()).into();
+        error.iter().flatten().fold(0.0, |max, e| max.max(e.abs()))
+    }
+
+    /// Largest error allowed in the camera's isometry, far below the
+    /// precision of the shaders.
+    const DRIFT_TOLERANCE: f64 = 1e-12;
+
+    #[test]
+    fn long_drag_stays_isometry() {
+        let mut camera = CameraTracker::new(1.5);
+        let tiling = TilingGenerator::try_from_schlafli(Some(4), Some(5)).unwrap();
+        camera.neighbours = Some(tiling.neighbours());
+        // The same moves composed without corrections, until they drift.
+        let mut uncorrected = Matrix3::one();
+        let mut drifted_at = None;
+        // A xorshift generator, so that the path is the same every run.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut random = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0
+        };
+        for step in 0..500_000 {
+            // Wander off along a winding path that never comes back, with the
+            // camera moved back over the tile around the origin on the way,
+            // as in the app.
+            let delta = Vector2::new(0.03 + random() * 0.02, random() * 0.05);
+            let angle = if step % 7 == 0 { random() * 0.1 } else { 0.0 };
+            camera.translate(delta);
+            camera.rotate(angle);
+            let recentred = std::mem::replace(&mut camera.recentred, Matrix3::one());
+            let error = isometry_error(camera.isometry());
+            assert!(error < DRIFT_TOLERANCE, "drifted by {} at {}", error, step);
+            if drifted_at.is_none() {
+                uncorrected = Matrix3::from_angle_z(Rad(angle))
+                    * translation(delta)
+                    * uncorrected
+                    * recentred;
+                if isometry_error(uncorrected) >= DRIFT_TOLERANCE {
+                    drifted_at = Some(step);
+                }
+            }
+        }
+        // Each symmetry the camera is moved back by magnifies the errors of
+        // the uncorrected moves, which soon pass the tolerance.
+        assert!(drifted_at.is_some(), "the uncorrected moves did not drift");
+        assert!(camera.isometry().z.z > 0.0);
+        assert!((camera.isometry().determinant() - 1.0).abs() < 1e-9);
+    }
+
+    /// Point of the screen, in clip coordinates, at which `camera` shows
+    /// `v`, for projections with a pole.
+    fn project(camera: &CameraTracker, v: Vector3<f64>) -> Vector2<f64> {
+        let view = Matrix3::from_angle_z(Rad(-camera.turn)) * camera.isometry();
+        let p = project_pole(camera.projection.pole().unwrap(), view * v);
+        (camera.viewport * p.extend(0.0).extend(1.0))
+            .truncate()
+            .truncate()
+    }
+
+    #[test]
+    fn picks_tile_under_point() {
+        for projection in [Projection::Poincare, Projection::Klein] {
+            let mut camera = CameraTracker::new(1.5);
+            camera.set_projection(projection);
+            camera.translate(Vector2::new(0.4, -0.2));
+            camera.rotate(0.3);
+            for (p, q) in [(Some(4), Some(5)), (None, Some(4))] {
+                let tiling = TilingGenerator::try_from_schlafli(p, q).unwrap();
+                let mesh = tiling.generate(crate::COLORS, 3);
+                for part in &mesh.parts {
+                    // A point a little way from the centre of the first
+                    // triangle of the piece towards its first vertex.
+                    let t = &mesh.index[part.indices.start as usize..][..3];
+                    let v: Vector3<f64> = [t[0], t[0], t[1], t[2]]
+                        .iter()
+                        .map(|&k| Vector3::from(mesh.vertex[k as usize].pos).cast().unwrap())
+                        .sum();
+                    let v = v / (v.z * v.z - v.truncate().magnitude2()).sqrt();
+                    for i in part.instances.clone() {
+                        let v = mesh.placed[i as usize].transform * v;
+                        let screen = project(&camera, v);
+                        let point = camera.unproject(screen).unwrap();
+                        assert_eq!(
+                            mesh.pick(point),
+                            Some(i as usize),
+                            "{:?} {:?} {:?}",
+                            projection,
+                            p,
+                            q
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ideal_boundary_picks_nothing() {
+        let mut camera = CameraTracker::new(1.0);
+        camera.translate(Vector2::new(0.4, -0.2));
+        for (p, q) in [(Some(4), Some(5)), (Some(4), None), (None, Some(4))] {
+            let tiling = TilingGenerator::try_from_schlafli(p, q).unwrap();
+            let mesh = tiling.generate(crate::COLORS, 3);
+            let mut ideal: Vec<_> = (0..64)
+                .map(|k| {
+                    let (sin, cos) = (TAU * k as f64 / 64.0).sin_cos();
+                    // The boundary of the Poincaré disk lies at the edge of
+                    // the image, where rounding leaves at most a point far
+                    // beyond the tiles.
+                    let seen = camera.unproject(Vector2::new(cos, sin));
+                    assert_eq!(seen.and_then(|v| mesh.pick(v)), None);
+                    Vector3::new(cos, sin, 1.0)
+                })
+                .collect();
+            // The ideal vertices of the tiles, where the pieces of tilings
+            // with ideal vertices meet.
+            for placed in &mesh.placed[..20] {
+                let vertices = tiling.vertices().into_iter();
+                ideal.extend(
+                    vertices
+                        .filter(|&v| minkowski(v, v).abs() < 1e-9)
+                        .map(|v| placed.transform * v),
+                );
+            }
+            for v in ideal {
+                assert_eq!(mesh.pick(v), None, "{:?} {:?} at {:?}", p, q, v);
+            }
+        }
+    }
+
+    #[test]
+    fn orthonormalizing_an_isometry_keeps_it() {
+        let m = Matrix3::from_angle_z(Rad(0.7)) * translation(Vector2::new(1.5, -0.4));
+        let error: [[f64; 3]; 3] = (lorentz_orthonormalize(m) - m).into();
+        assert!(error.iter().flatten().all(|e| e.abs() < 1e-12));
+    }
+
+    #[test]
+    fn flight_brings_target_to_centre() {
+        let mut camera = CameraTracker::new(1.5);
+        let tiling = TilingGenerator::try_from_schlafli(Some(4), Some(5)).unwrap();
+        camera.neighbours = Some(tiling.neighbours());
+        camera.translate(Vector2::new(0.2, -0.1));
+        camera.rotate(0.4);
+        // A point six units away, a few tiles' widths, so that the camera
+        // is moved back several times on the way.
+        let target =
+            lorentz_inverse(camera.isometry()) * translation(Vector2::new(-168.0, 112.0)).z;
+        camera.fly_to(target, 0.0, 1000.0);
+        let mut now = 0.0;
+        while camera.animate(now) {
+            now += 16.0;
+        }
+        let seen = camera.isometry() * lorentz_inverse(camera.recentred) * target;
+        assert!(camera.recentred != Matrix3::one());
+        assert!(seen.truncate().magnitude() < 1e-6, "{:?}", seen);
+        assert!(isometry_error(camera.isometry()) < DRIFT_TOLERANCE);
+    }
+
+    #[test]
+    fn mirrors_are_not_isometries() {
+        let m = translation(Vector2::new(0.3, 0.8));
+        assert!(checked_isometry(m).is_ok());
+        let mirror = Matrix3::from_diagonal(Vector3::new(1.0, -1.0, 1.0));
+        for m in [mirror, mirror * m, -Matrix3::one()] {
+            assert_eq!(checked_isometry(m), Err(IsometryError::NotIsometry));
+        }
+    }
+
+    #[test]
+    fn zooming_keeps_point_in_place() {
+        let mut camera = CameraTracker::new(1.5);
+        camera.set_projection(Projection::HalfPlane);
+        let at = Vector2::new(0.6, -0.3);
+        // `unview` takes normalized device coordinates, whose x runs across
+        // the width of the screen.
+        let ndc = Vector2::new(at.x / 1.5, at.y);
+        let fixed = camera.unview(ndc);
+        camera.zoom_view(2.5, at);
+        assert!((camera.unview(ndc) - fixed).magnitude() < 1e-12);
+        assert!((camera.zoom() - 2.5).abs() < 1e-12);
+        let view = (camera.view_centre, camera.view_scale);
+        for factor in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            camera.zoom_view(factor, at);
+            assert_eq!((camera.view_centre, camera.view_scale), view);
+        }
+        for _ in 0..2000 {
+            camera.zoom_view(0.5, at);
+        }
+        assert!((camera.zoom() / MIN_ZOOM - 1.0).abs() < 1e-12);
+        assert!((camera.unview(ndc) - fixed).magnitude() < 1e-9);
+    }
+}
@@ -0,0 +1,138 @@
+use std::ops::Deref;
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::{Matrix3, One};
+use wgpu::{util::DeviceExt, Device, Queue};
+
+use crate::palette::{PaletteBindGroup, PaletteBindGroupLayout, PALETTE_SIZE};
+use crate::tiling::{Domain, DomainPaint};
+use crate::Color;
+
+/// Largest number of sides of a domain, which must match the size of the
+/// array in the shader.
+pub const DOMAIN_SIDES: usize = 16;
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct DomainUniform {
+    sides: [[f32; 4]; DOMAIN_SIDES],
+    /// Columns of the transform taking the tiles around the origin to those
+    /// they are coloured as.
+    origin: [[f32; 4]; 3],
+    centre: [f32; 4],
+    count: u32,
+    parity: u32,
+    slots: u32,
+    _padding: u32,
+}
+impl DomainUniform {
+    /// Returns the uniform for `domain` coloured from `slots` palette slots,
+    /// unless it has too many sides.
+    pub fn new(domain: &Domain, slots: usize) -> Option<Self> {
+        if domain.sides.len() > DOMAIN_SIDES {
+            return None;
+        }
+        let mut uniform = DomainUniform::zeroed();
+        for (side, n) in uniform.sides.iter_mut().zip(&domain.sides) {
+            *side = n.cast::<f32>().unwrap().extend(0.0).into();
+        }
+        uniform.set_origin(Matrix3::one());
+        uniform.centre = domain.centre.cast::<f32>().unwrap().extend(0.0).into();
+        uniform.count = domain.sides.len() as u32;
+        uniform.parity = (domain.paint == DomainPaint::Parity) as u32;
+        uniform.slots = slots as u32;
+        Some(uniform)
+    }
+
+    fn set_origin(&mut self, origin: Matrix3<f64>) {
+        let origin = origin.cast::<f32>().unwrap();
+        for (column, v) in self.origin.iter_mut().zip([origin.x, origin.y, origin.z]) {
+            *column = v.extend(0.0).into();
+        }
+    }
+}
+
+pub struct DomainBindGroupLayout {
+    inner: wgpu::BindGroupLayout,
+}
+impl DomainBindGroupLayout {
+    pub fn new(device: &Device) -> Self {
+        DomainBindGroupLayout {
+            inner: device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("domain bind_group_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            }),
+        }
+    }
+}
+impl Deref for DomainBindGroupLayout {
+    type Target = wgpu::BindGroupLayout;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+/// Tiling drawn per pixel, by folding every point back into a domain.
+pub struct FoldedTiling {
+    palette: PaletteBindGroup,
+    uniform: DomainUniform,
+    buffer: wgpu::Buffer,
+    domain: wgpu::BindGroup,
+}
+impl FoldedTiling {
+    /// Uploads `domain` coloured from `colors`, unless it has too many sides.
+    pub fn new(
+        device: &Device,
+        palette: &PaletteBindGroupLayout,
+        layout: &DomainBindGroupLayout,
+        domain: &Domain,
+        colors: &[Color],
+    ) -> Option<Self> {
+        let uniform = DomainUniform::new(domain, colors.len().min(PALETTE_SIZE))?;
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            contents: bytemuck::bytes_of(&uniform),
+        });
+        let domain = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        Some(FoldedTiling {
+            palette: PaletteBindGroup::new(device, palette, colors),
+            uniform,
+            buffer,
+            domain,
+        })
+    }
+
+    /// Colours each tile as the one `origin` takes it to, so that the
+    /// colours stay put when the camera is moved back over the tile around
+    /// the origin. Tiles coloured by parity don't need it, as the
+    /// camera is only moved back by rotations.
+    pub fn set_origin(&mut self, queue: &Queue, origin: Matrix3<f64>) {
+        self.uniform.set_origin(origin);
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&self.uniform));
+    }
+
+    /// Records the draw call of a triangle covering the screen.
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        rpass.set_bind_group(1, &self.palette, &[]);
+        rpass.set_bind_group(2, &self.domain, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,100 @@
+//! Tiles highlighted under the pointer or selected by clicking them.
+use cgmath::{Matrix3, Vector3};
+
+use crate::{lorentz_inverse, InstanceFlags, InstancedMesh};
+
+/// Point of the plane picked by the user, along with the face of the mesh
+/// under it when it was last picked.
+#[derive(Debug, Clone, Copy)]
+struct Picked {
+    point: Vector3<f64>,
+    face: Option<usize>,
+}
+impl Picked {
+    fn new(mesh: &InstancedMesh, point: Vector3<f64>) -> Self {
+        Picked {
+            point,
+            face: mesh.pick(point).map(|i| mesh.placed[i].face),
+        }
+    }
+}
+
+/// Faces picked by the user, kept as points of the plane inside them rather
+/// than as instances, so that they stay with the faces when the camera is
+/// moved back over the tile around the origin, and survive a change of
+/// depth. Every piece of a face is highlighted along with it.
+#[derive(Debug, Clone, Default)]
+pub struct Highlight {
+    hovered: Option<Picked>,
+    selected: Vec<Picked>,
+}
+impl Highlight {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the point under the pointer, returning whether that changes the
+    /// hovered face of `mesh`.
+    pub fn hover(&mut self, mesh: &InstancedMesh, point: Option<Vector3<f64>>) -> bool {
+        let before = self.hovered.and_then(|h| h.face);
+        self.hovered = point.map(|v| Picked::new(mesh, v));
+        before != self.hovered.and_then(|h| h.face)
+    }
+
+    /// Selects the face of `mesh` containing `point`, or deselects it if it
+    /// already was.
+    pub fn toggle(&mut self, mesh: &InstancedMesh, point: Vector3<f64>) {
+        let picked = Picked::new(mesh, point);
+        if picked.face.is_none() {
+            return;
+        }
+        let count = self.selected.len();
+        self.selected.retain(|s| s.face != picked.face);
+        if self.selected.len() == count {
+            self.selected.push(picked);
+        }
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selected.clear();
+    }
+
+    /// Finds the faces under the points again, once `mesh` has replaced the
+    /// mesh they were picked in.
+    pub fn repick(&mut self, mesh: &InstancedMesh) {
+        for picked in self.hovered.iter_mut().chain(&mut self.selected) {
+            *picked = Picked::new(mesh, picked.point);
+        }
+    }
+
+    /// Moves the points along with the plane when the camera has been moved
+    /// back by `symmetry`, as returned by
+    /// [`Camera::take_recentred`](crate::camera::Camera::take_recentred),
+    /// and finds the faces of `mesh` under them.
+    pub fn recentre(&mut self, mesh: &InstancedMesh, symmetry: Matrix3<f64>) {
+        let inverse = lorentz_inverse(symmetry);
+        for picked in self.hovered.iter_mut().chain(&mut self.selected) {
+            *picked = Picked::new(mesh, inverse * picked.point);
+        }
+    }
+
+    /// Returns the [`InstanceFlags`] of every instance of `mesh`, which must
+    /// be the mesh the faces were last picked in.
+    pub fn flags(&self, mesh: &InstancedMesh) -> Vec<u32> {
+        let hovered = self.hovered.and_then(|h| h.face);
+        mesh.placed
+            .iter()
+            .map(|placed| {
+                let face = Some(placed.face);
+                let mut flags = 0;
+                if face == hovered {
+                    flags |= InstanceFlags::HOVERED;
+                }
+                if self.selected.iter().any(|s| s.face == face) {
+                    flags |= InstanceFlags::SELECTED;
+                }
+                flags
+            })
+            .collect()
+    }
+}
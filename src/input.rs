@@ -0,0 +1,272 @@
+//! Moving the camera with keys and gamepads, held down over several frames
+//! rather than dragged.
+use cgmath::{InnerSpace, Vector2};
+
+/// Speed of moving through the plane, in screen units per second.
+const MOVE_SPEED: f64 = 1.0;
+
+/// Speed of turning the view, in radians per second.
+const TURN_SPEED: f64 = std::f64::consts::FRAC_PI_2;
+
+/// Factor the view is zoomed by per second.
+const ZOOM_SPEED: f64 = 2.0;
+
+/// Longest step between frames, in milliseconds, so that a late frame
+/// doesn't throw the camera far off.
+const MAX_STEP: f64 = 100.0;
+
+/// How far a gamepad stick must be pushed before it counts, as they seldom
+/// rest exactly at the centre.
+const DEAD_ZONE: f64 = 0.15;
+
+/// What a key held down does.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Action {
+    Forward,
+    Back,
+    Left,
+    Right,
+    /// Turns the view counterclockwise.
+    TurnLeft,
+    TurnRight,
+    ZoomIn,
+    ZoomOut,
+}
+impl Action {
+    /// Action of the key named `key`, as in `KeyboardEvent.key`: the arrow
+    /// keys and WASD move, Q and E turn, and + and - zoom.
+    pub fn from_key(key: &str) -> Option<Self> {
+        Some(match key.to_ascii_lowercase().as_str() {
+            "arrowup" | "w" => Action::Forward,
+            "arrowdown" | "s" => Action::Back,
+            "arrowleft" | "a" => Action::Left,
+            "arrowright" | "d" => Action::Right,
+            "q" => Action::TurnLeft,
+            "e" => Action::TurnRight,
+            "+" | "=" => Action::ZoomIn,
+            "-" | "_" => Action::ZoomOut,
+            _ => return None,
+        })
+    }
+}
+
+/// Change of the camera over one frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Step {
+    /// Move through the plane, in screen units with y pointing up.
+    pub offset: Vector2<f64>,
+    /// Counterclockwise turn of the view, in radians.
+    pub angle: f64,
+    /// Factor to zoom the view by.
+    pub zoom: f64,
+}
+
+/// Position of the sticks of a gamepad, each axis from -1 to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Sticks {
+    /// Direction to move in, with y pointing forward.
+    motion: Vector2<f64>,
+    /// Counterclockwise turn.
+    turn: f64,
+    /// Zoom, positive to zoom in.
+    zoom: f64,
+}
+impl Sticks {
+    const REST: Sticks = Sticks {
+        motion: Vector2::new(0.0, 0.0),
+        turn: 0.0,
+        zoom: 0.0,
+    };
+}
+
+/// Keys held down and gamepad sticks, turned into steps of the camera as
+/// frames are drawn.
+#[derive(Debug, Clone)]
+pub struct Input {
+    held: Vec<Action>,
+    sticks: Sticks,
+    /// Time of the last step, while any input is active.
+    last: Option<f64>,
+}
+impl Input {
+    pub fn new() -> Self {
+        Input {
+            held: Vec::new(),
+            sticks: Sticks::REST,
+            last: None,
+        }
+    }
+
+    /// Holds `action` down, returning whether it wasn't already, as keys
+    /// repeat.
+    pub fn press(&mut self, action: Action) -> bool {
+        if self.held.contains(&action) {
+            return false;
+        }
+        self.held.push(action);
+        true
+    }
+
+    pub fn release(&mut self, action: Action) {
+        self.held.retain(|&a| a != action);
+    }
+
+    /// Lets go of every key, as when the page loses focus and the keys
+    /// released meanwhile are never heard of.
+    pub fn release_all(&mut self) {
+        self.held.clear();
+    }
+
+    /// Sets the position of the gamepad sticks: the direction to move in,
+    /// with y pointing forward, the counterclockwise turn and the zoom, each
+    /// from -1 to 1.
+    pub fn set_sticks(&mut self, motion: Vector2<f64>, turn: f64, zoom: f64) {
+        let dead = |x: f64| if x.abs() < DEAD_ZONE { 0.0 } else { x };
+        let motion = if motion.magnitude() < DEAD_ZONE {
+            Vector2::new(0.0, 0.0)
+        } else {
+            motion
+        };
+        self.sticks = Sticks {
+            motion,
+            turn: dead(turn),
+            zoom: dead(zoom),
+        };
+    }
+
+    /// Whether any key or stick is moving the camera.
+    pub fn is_active(&self) -> bool {
+        !self.held.is_empty() || self.sticks != Sticks::REST
+    }
+
+    /// Steps the camera on to the time `now`, or `None` if no input is
+    /// active. The first step after the input becomes active is empty, as
+    /// it only starts the clock.
+    pub fn advance(&mut self, now: f64) -> Option<Step> {
+        if !self.is_active() {
+            self.last = None;
+            return None;
+        }
+        let dt = match self.last {
+            Some(last) => (now - last).clamp(0.0, MAX_STEP) / 1000.0,
+            None => 0.0,
+        };
+        self.last = Some(now);
+
+        let Sticks {
+            mut motion,
+            mut turn,
+            mut zoom,
+        } = self.sticks;
+        for action in &self.held {
+            match action {
+                Action::Forward => motion.y += 1.0,
+                Action::Back => motion.y -= 1.0,
+                Action::Left => motion.x -= 1.0,
+                Action::Right => motion.x += 1.0,
+                Action::TurnLeft => turn += 1.0,
+                Action::TurnRight => turn -= 1.0,
+                Action::ZoomIn => zoom += 1.0,
+                Action::ZoomOut => zoom -= 1.0,
+            }
+        }
+        // Moving diagonally is no faster than moving straight.
+        if motion.magnitude() > 1.0 {
+            motion = motion.normalize();
+        }
+        Some(Step {
+            offset: motion * MOVE_SPEED * dt,
+            angle: turn.clamp(-1.0, 1.0) * TURN_SPEED * dt,
+            zoom: ZOOM_SPEED.powf(zoom.clamp(-1.0, 1.0) * dt),
+        })
+    }
+}
+impl Default for Input {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Starts the clock with `input` at time 0.
+    fn started(mut input: Input) -> Input {
+        assert_eq!(input.advance(0.0).unwrap().offset, Vector2::new(0.0, 0.0));
+        input
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-12
+    }
+
+    #[test]
+    fn held_keys_move_at_their_speed() {
+        let mut input = Input::new();
+        input.press(Action::Forward);
+        let step = started(input.clone()).advance(50.0).unwrap();
+        assert!(close(step.offset.x, 0.0) && close(step.offset.y, 0.05 * MOVE_SPEED));
+
+        // Moving diagonally is no faster, and opposite keys cancel out.
+        input.press(Action::Right);
+        let step = started(input.clone()).advance(50.0).unwrap();
+        assert!(close(step.offset.magnitude(), 0.05 * MOVE_SPEED));
+        assert!(close(step.offset.x, step.offset.y));
+        input.press(Action::Back);
+        let step = started(input.clone()).advance(50.0).unwrap();
+        assert!(close(step.offset.x, 0.05 * MOVE_SPEED) && close(step.offset.y, 0.0));
+
+        let mut input = Input::new();
+        input.press(Action::TurnRight);
+        input.press(Action::ZoomIn);
+        let step = started(input).advance(50.0).unwrap();
+        assert!(close(step.angle, -0.05 * TURN_SPEED));
+        assert!(close(step.zoom, ZOOM_SPEED.powf(0.05)));
+    }
+
+    #[test]
+    fn sticks_have_a_dead_zone() {
+        let mut input = Input::new();
+        input.set_sticks(Vector2::new(0.1, -0.1), 0.1, -0.1);
+        assert!(!input.is_active());
+        assert_eq!(input.advance(0.0), None);
+
+        // Past it, the sticks move as far as they are pushed.
+        input.set_sticks(Vector2::new(0.3, 0.0), 0.0, -0.5);
+        let step = started(input).advance(100.0).unwrap();
+        assert!(close(step.offset.x, 0.03 * MOVE_SPEED) && close(step.offset.y, 0.0));
+        assert!(close(step.zoom, ZOOM_SPEED.powf(-0.05)));
+    }
+
+    #[test]
+    fn moves_follow_the_time_between_frames() {
+        let mut input = Input::new();
+        input.press(Action::Forward);
+        // The same time passing over more frames moves as far.
+        let mut slow = started(input.clone());
+        let mut fast = started(input.clone());
+        let far = slow.advance(80.0).unwrap().offset.y;
+        let near: f64 = (1..=5)
+            .map(|i| fast.advance(16.0 * i as f64).unwrap().offset.y)
+            .sum();
+        assert!(close(far, near));
+
+        // A late frame moves no further than `MAX_STEP`, and a clock going
+        // back doesn't move at all.
+        let mut late = started(input.clone());
+        let step = late.advance(10.0 * MAX_STEP).unwrap();
+        assert!(close(step.offset.y, MAX_STEP / 1000.0 * MOVE_SPEED));
+        assert_eq!(late.advance(0.0).unwrap().offset.y, 0.0);
+
+        // Letting go stops the clock, which starts again with the next key.
+        input.release(Action::Forward);
+        assert_eq!(input.advance(1000.0), None);
+        input.press(Action::Back);
+        assert_eq!(input.advance(5000.0).unwrap().offset.y, 0.0);
+        assert!(close(
+            input.advance(5020.0).unwrap().offset.y,
+            -0.02 * MOVE_SPEED
+        ));
+    }
+}
@@ -1,26 +1,51 @@
+use std::collections::HashMap;
 use std::num::ParseIntError;
+use std::ops::Range;
 use std::str::FromStr;
 
 use bytemuck::{Pod, Zeroable};
-use cgmath::{InnerSpace, Matrix3, Vector2, Vector3};
+use cgmath::{InnerSpace, Matrix, Matrix3, One, Vector2, Vector3};
 use log::warn;
 use parking_lot::Mutex;
-use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::prelude::{wasm_bindgen, JsError};
 use wgpu::util::DeviceExt;
-use wgpu::Device;
+use wgpu::{Device, Queue};
 
 pub mod camera;
+pub mod domain;
+pub mod highlight;
+pub mod input;
+pub mod models;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod native;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod offscreen;
+pub mod palette;
 pub mod pipeline;
 pub mod surface;
+pub mod svg;
 pub mod tiling;
 
 pub mod window;
 
-use camera::Camera;
-use pipeline::{Pipeline, Projection};
+use camera::{Camera, IsometryError};
+use domain::FoldedTiling;
+use highlight::Highlight;
+use input::{Action, Input};
+use models::{Layer, LayerBindGroup, ModelLines};
+use palette::{PaletteBindGroup, PaletteBindGroupLayout};
+use pipeline::{Pipeline, Projection, Renderer};
 use surface::{State, Surface};
-use tiling::TilingGenerator;
-use window::{AppWindow, Window};
+use svg::{SvgError, SvgView};
+use tiling::{minkowski, Tiling, TilingGenerator, TriangleTiling};
+use window::Window;
+
+/// Window the app draws into: a canvas in the browser, and a winit window on
+/// the desktop.
+#[cfg(target_arch = "wasm32")]
+pub type PlatformWindow = window::AppWindow;
+#[cfg(not(target_arch = "wasm32"))]
+pub type PlatformWindow = native::NativeWindow;
 
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
@@ -51,49 +76,298 @@ impl From<Color> for [f32; 3] {
     }
 }
 
+/// Vertex of a prototile.
 #[derive(Debug, Clone, Copy, Zeroable, Pod)]
 #[repr(C)]
 pub struct Vertex {
     pub pos: [f32; 3],
-    pub color: [f32; 3],
 }
 impl Vertex {
     pub const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
         array_stride: std::mem::size_of::<Self>() as _,
         step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &[wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x3,
+            offset: 0,
+            shader_location: 0,
+        }],
+    };
+}
+
+/// Copy of a prototile, moved by `transform` and coloured from the palette
+/// slot `color`.
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct Instance {
+    pub transform: [[f32; 3]; 3],
+    pub color: u32,
+}
+impl Instance {
+    pub const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Self>() as _,
+        step_mode: wgpu::VertexStepMode::Instance,
         attributes: &[
             wgpu::VertexAttribute {
                 format: wgpu::VertexFormat::Float32x3,
                 offset: 0,
-                shader_location: 0,
+                shader_location: 1,
             },
             wgpu::VertexAttribute {
                 format: wgpu::VertexFormat::Float32x3,
                 offset: 3 * 4,
-                shader_location: 1,
+                shader_location: 2,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x3,
+                offset: 6 * 4,
+                shader_location: 3,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Uint32,
+                offset: 9 * 4,
+                shader_location: 4,
             },
         ],
     };
 }
 
+/// Flags of an [`Instance`] read by the fragment shader, kept in a buffer of
+/// their own so that they can change without regenerating the mesh. WebGL
+/// has no storage buffers, so they are passed as an instance attribute.
+pub struct InstanceFlags;
+impl InstanceFlags {
+    /// The face the instance is a piece of is under the pointer.
+    pub const HOVERED: u32 = 1;
+    /// The face has been selected by clicking it.
+    pub const SELECTED: u32 = 2;
+
+    pub const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<u32>() as _,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &[wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Uint32,
+            offset: 0,
+            shader_location: 5,
+        }],
+    };
+}
+
+/// Where the walk placed an [`Instance`], kept at full precision for finding
+/// the tile under a point.
+#[derive(Debug, Clone)]
+pub struct Placed {
+    /// Steps taken from the tile around the origin to reach the tile.
+    pub address: Vec<u16>,
+    pub transform: Matrix3<f64>,
+    /// Number of the face of the tiling the instance is a piece of, shared
+    /// by the instances of its other pieces.
+    pub face: usize,
+}
+
+/// Prototile drawn once per instance in `instances`, using the indices in
+/// `indices`.
+#[derive(Debug, Clone)]
+pub struct Part {
+    pub indices: Range<u32>,
+    pub instances: Range<u32>,
+}
+
+/// Prototiles together with their copies making up a tiling, and the palette
+/// they are coloured from.
+#[derive(Debug, Clone, Default)]
+pub struct InstancedMesh {
+    pub vertex: Vec<Vertex>,
+    pub index: Vec<u32>,
+    pub instances: Vec<Instance>,
+    /// Where each instance was placed, in the same order.
+    pub placed: Vec<Placed>,
+    pub parts: Vec<Part>,
+    pub palette: Vec<Color>,
+}
+impl InstancedMesh {
+    /// Returns the index of an instance covering `v`, a point of the
+    /// hyperboloid.
+    pub fn pick(&self, v: Vector3<f64>) -> Option<usize> {
+        let point = |i: u32| {
+            Vector3::from(self.vertex[i as usize].pos)
+                .cast::<f64>()
+                .unwrap()
+        };
+        let klein = |v: Vector3<f64>| v.truncate() / v.z;
+        for part in &self.parts {
+            let indices = &self.index[part.indices.start as usize..part.indices.end as usize];
+            let triangles = indices
+                .chunks_exact(3)
+                .map(|t| [klein(point(t[0])), klein(point(t[1])), klein(point(t[2]))])
+                .collect::<Vec<_>>();
+            // Points of the prototile are no further from the origin than its
+            // furthest vertex.
+            let reach = indices.iter().map(|&i| point(i).z).fold(1.0, f64::max);
+            for i in part.instances.clone() {
+                let u = lorentz_inverse(self.placed[i as usize].transform) * v;
+                // The triangles of the mesh are flat, so they are straight in
+                // the Klein model.
+                if u.z <= reach && triangles.iter().any(|t| in_triangle(t, klein(u))) {
+                    return Some(i as usize);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Prototiles of an [`InstancedMesh`], without their copies.
+#[derive(Debug, Clone)]
+struct Prototiles {
+    vertex: Vec<Vertex>,
+    index: Vec<u32>,
+    parts: Vec<Part>,
+}
+impl Prototiles {
+    fn new(mesh: &InstancedMesh) -> Self {
+        Prototiles {
+            vertex: mesh.vertex.clone(),
+            index: mesh.index.clone(),
+            parts: mesh.parts.clone(),
+        }
+    }
+
+    /// Splits every triangle into four, `levels` times, putting the new
+    /// vertices on the hyperboloid so that the sides of the tiles stay
+    /// geodesics.
+    fn subdivided(&self, levels: u32) -> Prototiles {
+        let mut mesh = self.clone();
+        for _ in 0..levels {
+            let mut midpoints = HashMap::new();
+            let vertex = &mut mesh.vertex;
+            let mut midpoint = |a: u32, b: u32| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    let u = Vector3::from(vertex[a as usize].pos);
+                    let v = Vector3::from(vertex[b as usize].pos);
+                    let m = u + v;
+                    let m = m / (m.z * m.z - m.x * m.x - m.y * m.y).sqrt();
+                    vertex.push(Vertex { pos: m.into() });
+                    vertex.len() as u32 - 1
+                })
+            };
+            let mut index = Vec::with_capacity(4 * mesh.index.len());
+            for t in mesh.index.chunks_exact(3) {
+                let (a, b, c) = (t[0], t[1], t[2]);
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                index.extend_from_slice(&[a, ab, ca, ab, b, bc, ca, bc, c, ab, bc, ca]);
+            }
+            mesh.index = index;
+            for part in &mut mesh.parts {
+                part.indices = 4 * part.indices.start..4 * part.indices.end;
+            }
+        }
+        mesh
+    }
+}
+
+/// Whether `p` lies in the triangle `t`, given counterclockwise or not.
+fn in_triangle(t: &[Vector2<f64>; 3], p: Vector2<f64>) -> bool {
+    let side = |a: Vector2<f64>, b: Vector2<f64>| (b - a).perp_dot(p - a);
+    let sides = [side(t[0], t[1]), side(t[1], t[2]), side(t[2], t[0])];
+    sides.iter().all(|&s| s >= 0.0) || sides.iter().all(|&s| s <= 0.0)
+}
+
 pub struct Mesh {
     vertex: wgpu::Buffer,
     index: wgpu::Buffer,
+    instances: wgpu::Buffer,
+    /// [`InstanceFlags`] of each instance.
+    flags: wgpu::Buffer,
+    parts: Vec<Part>,
+    palette: PaletteBindGroup,
+    /// Prototiles as generated, split again whenever the detail changes.
+    prototiles: Prototiles,
 }
 impl Mesh {
-    pub fn new(device: &Device, (vertex, index): (Vec<Vertex>, Vec<u32>)) -> Self {
+    /// Uploads `mesh`, with the triangles of its prototiles split `levels`
+    /// times as by [`Mesh::set_detail`].
+    pub fn new(
+        device: &Device,
+        layout: &PaletteBindGroupLayout,
+        mesh: &InstancedMesh,
+        levels: u32,
+    ) -> Self {
+        let prototiles = Prototiles::new(mesh);
+        let (vertex, index, parts) =
+            Self::upload_prototiles(device, &prototiles.subdivided(levels));
+
+        let instances = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            contents: bytemuck::cast_slice(&mesh.instances),
+        });
+
+        let flags = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            contents: bytemuck::cast_slice(&vec![0u32; mesh.instances.len()]),
+        });
+
+        let palette = PaletteBindGroup::new(device, layout, &mesh.palette);
+        Mesh {
+            vertex,
+            index,
+            instances,
+            flags,
+            parts,
+            palette,
+            prototiles,
+        }
+    }
+
+    fn upload_prototiles(
+        device: &Device,
+        prototiles: &Prototiles,
+    ) -> (wgpu::Buffer, wgpu::Buffer, Vec<Part>) {
         let vertex = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
             usage: wgpu::BufferUsages::VERTEX,
-            contents: bytemuck::cast_slice(&vertex),
+            contents: bytemuck::cast_slice(&prototiles.vertex),
         });
 
         let index = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: None,
             usage: wgpu::BufferUsages::INDEX,
-            contents: bytemuck::cast_slice(&index),
+            contents: bytemuck::cast_slice(&prototiles.index),
         });
-        Mesh { vertex, index }
+        (vertex, index, prototiles.parts.clone())
+    }
+
+    /// Splits the triangles of the prototiles into four, `levels` times, as
+    /// generated, keeping the instances.
+    pub fn set_detail(&mut self, device: &Device, levels: u32) {
+        let (vertex, index, parts) =
+            Self::upload_prototiles(device, &self.prototiles.subdivided(levels));
+        self.vertex = vertex;
+        self.index = index;
+        self.parts = parts;
+    }
+
+    /// Replaces the instances, keeping their number.
+    pub fn set_instances(&self, queue: &Queue, instances: &[Instance]) {
+        queue.write_buffer(&self.instances, 0, bytemuck::cast_slice(instances));
+    }
+
+    /// Replaces the [`InstanceFlags`] of the instances, one for each.
+    pub fn set_flags(&self, queue: &Queue, flags: &[u32]) {
+        queue.write_buffer(&self.flags, 0, bytemuck::cast_slice(flags));
+    }
+
+    /// Records the draw calls of every prototile.
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        rpass.set_bind_group(1, &self.palette, &[]);
+        rpass.set_vertex_buffer(0, self.vertex.slice(..));
+        rpass.set_vertex_buffer(1, self.instances.slice(..));
+        rpass.set_vertex_buffer(2, self.flags.slice(..));
+        rpass.set_index_buffer(self.index.slice(..), wgpu::IndexFormat::Uint32);
+        for part in &self.parts {
+            rpass.draw_indexed(part.indices.clone(), 0, part.instances.clone());
+        }
     }
 }
 
@@ -107,8 +381,32 @@ pub fn translation(pos: Vector2<f64>) -> Matrix3<f64> {
     )
 }
 
+/// Inverse of an isometry of the hyperboloid, which is its transpose
+/// conjugated by the Minkowski metric.
+pub fn lorentz_inverse(m: Matrix3<f64>) -> Matrix3<f64> {
+    let t = m.transpose();
+    Matrix3::from_cols(
+        Vector3::new(t.x.x, t.x.y, -t.x.z),
+        Vector3::new(t.y.x, t.y.y, -t.y.z),
+        Vector3::new(-t.z.x, -t.z.y, t.z.z),
+    )
+}
+
+/// An isometry of the hyperboloid close to `m`, found by Gram–Schmidt with
+/// the Minkowski inner product, starting from the image of the origin. This
+/// undoes the rounding errors that build up in products of isometries.
+pub fn lorentz_orthonormalize(m: Matrix3<f64>) -> Matrix3<f64> {
+    let z = m.z / (-minkowski(m.z, m.z)).sqrt();
+    let x = m.x + z * minkowski(m.x, z);
+    let x = x / minkowski(x, x).sqrt();
+    let y = m.y + z * minkowski(m.y, z) - x * minkowski(m.y, x);
+    let y = y / minkowski(y, y).sqrt();
+    Matrix3::from_cols(x, y, z)
+}
+
+/// Palette of the tilings coloured by fragment.
 #[rustfmt::skip]
-const COLORS: &[Color] = &[
+pub const COLORS: &[Color] = &[
     Color { r: 255, g:   0, b:   0 },
     Color { r: 176, g: 196, b: 222 },
     Color { r:  48, g: 191, b: 190 },
@@ -118,44 +416,172 @@ const COLORS: &[Color] = &[
     Color { r:  17, g: 100, b: 179 },
 ];
 
+/// Palette for tilings coloured by parity, such as [`TriangleTiling`].
+#[rustfmt::skip]
+const PARITY_COLORS: &[Color] = &[
+    Color { r:  24, g:  24, b:  24 },
+    Color { r: 232, g: 232, b: 232 },
+];
+
+/// Most times the triangles of the meshes are split when zoomed in, each
+/// making four times as many.
+const MAX_DETAIL: u32 = 4;
+
+/// Colour of the edges of the dual tiling drawn over the tiling.
+const DUAL_COLOR: Color = Color {
+    r: 255,
+    g: 191,
+    b: 0,
+};
+
+/// Tile under a point of the screen, found by [`App::pick`].
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct Pick {
+    address: Vec<u16>,
+    color: u32,
+    point: Vector3<f64>,
+    transform: Matrix3<f64>,
+}
+#[wasm_bindgen]
+impl Pick {
+    /// Address of the tile in the fragment tree: the sides crossed on the
+    /// way to it from the tile around the origin, or the mirrors reflected
+    /// across for triangle tilings.
+    #[wasm_bindgen(getter)]
+    pub fn address(&self) -> Vec<u16> {
+        self.address.clone()
+    }
+
+    /// Palette slot the tile is coloured from.
+    #[wasm_bindgen(getter)]
+    pub fn color(&self) -> u32 {
+        self.color
+    }
+
+    /// Point picked, on the hyperboloid, before the camera's transform.
+    #[wasm_bindgen(getter)]
+    pub fn point(&self) -> Vec<f64> {
+        vec![self.point.x, self.point.y, self.point.z]
+    }
+}
+impl Pick {
+    /// Transform taking the prototile to the tile.
+    pub fn transform(&self) -> Matrix3<f64> {
+        self.transform
+    }
+}
+
 #[wasm_bindgen]
 pub struct App {
     state: State,
-    surface: Surface<AppWindow>,
+    surface: Surface<PlatformWindow>,
     pipeline: Pipeline,
+    fold_pipeline: Pipeline,
+    overlay_pipeline: Pipeline,
     camera: Mutex<Camera>,
 
-    tiling: TilingGenerator,
-    mesh: Mesh,
+    tiling: Box<dyn Tiling>,
+    colors: &'static [Color],
+    depth: usize,
+    renderer: Renderer,
+    mesh: Option<Mesh>,
+    /// Tiles up to the depth, kept for picking even when drawn per pixel.
+    generated: InstancedMesh,
+    /// Times the triangles of the meshes are split, for the zoom.
+    detail: u32,
+    highlight: Mutex<Highlight>,
+    /// Keys and gamepad sticks moving the camera between frames.
+    input: Mutex<Input>,
+    /// Tiling drawn per pixel instead of the mesh, when it can be.
+    folded: Option<FoldedTiling>,
+    /// Product of the symmetries the camera was moved back by since the
+    /// tiling was set, taking the tiles around the origin to those they were
+    /// first drawn as.
+    recentred: Matrix3<f64>,
+
+    /// Dual of the tiling, if it has one.
+    dual: Option<TilingGenerator>,
+    overlay: bool,
+    dual_mesh: Option<Mesh>,
+
+    /// Whether the 3D view of the hyperboloid shows the Poincaré disk and
+    /// the Klein plane, with the rays projecting the sheet onto them.
+    models: bool,
+    model_lines: ModelLines,
+    /// Bind groups selecting each [`Layer`], in order.
+    layers: Vec<LayerBindGroup>,
 }
-#[wasm_bindgen]
 impl App {
-    #[wasm_bindgen(constructor)]
-    pub async fn new(tiling: TilingGenerator, window: AppWindow) -> Self {
+    /// Sets up the app drawing `tiling` into `window`, as the constructor
+    /// does in the browser.
+    pub async fn with_window(tiling: TilingGenerator, window: PlatformWindow) -> Self {
         let (state, surface) = Surface::new(window).await;
-        let pipeline = Pipeline::new(
+        let pipeline = Pipeline::new(&state.device, Renderer::Mesh, surface.swapchain_format);
+        let fold_pipeline = Pipeline::with_layout(
             &state.device,
-            Projection::Poincare,
+            pipeline.layout.clone(),
+            Renderer::Fold,
             surface.swapchain_format,
         );
+        let overlay_pipeline = Pipeline::with_layout(
+            &state.device,
+            pipeline.layout.clone(),
+            Renderer::Overlay,
+            surface.swapchain_format,
+        );
+        let layers = Layer::ALL
+            .iter()
+            .map(|&layer| LayerBindGroup::new(&state.device, &pipeline.layout.layer, layer))
+            .collect();
+        let model_lines = ModelLines::new(&state.device);
         let camera = Camera::new(
             &state.device,
             &pipeline.layout.camera,
             surface.aspect_ratio(),
         );
 
-        let mesh = Mesh::new(&state.device, tiling.generate(COLORS, 5));
+        let generated = tiling.generate(COLORS, 5);
+        let mesh = Mesh::new(&state.device, &pipeline.layout.palette, &generated, 0);
+        let dual = tiling.try_dual().ok();
 
         App {
             state,
             surface,
             pipeline,
+            fold_pipeline,
+            overlay_pipeline,
             camera: Mutex::new(camera),
-            tiling,
-            mesh,
+            tiling: Box::new(tiling),
+            colors: COLORS,
+            depth: 5,
+            renderer: Renderer::Mesh,
+            mesh: Some(mesh),
+            generated,
+            detail: 0,
+            highlight: Mutex::new(Highlight::new()),
+            input: Mutex::new(Input::new()),
+            folded: None,
+            recentred: Matrix3::one(),
+            dual,
+            overlay: false,
+            dual_mesh: None,
+            models: false,
+            model_lines,
+            layers,
         }
     }
-
+}
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl App {
+    #[wasm_bindgen(constructor)]
+    pub async fn new(tiling: TilingGenerator, window: PlatformWindow) -> Self {
+        Self::with_window(tiling, window).await
+    }
+}
+#[wasm_bindgen]
+impl App {
     pub fn resize(&self, width: u32, height: u32) {
         let aspect_ratio = width as f64 / height as f64;
         self.camera
@@ -167,46 +593,372 @@ impl App {
     }
 
     pub fn update_delta(&self, x: f64, y: f64) {
+        let now = self.surface.window.now();
         self.camera
             .lock()
-            .update_delta(&self.state.queue, &self.surface, Vector2::new(x, -y));
+            .update_delta(&self.state.queue, &self.surface, Vector2::new(x, -y), now);
+    }
+
+    /// Orbits the 3D view of the hyperboloid while dragging.
+    pub fn update_orbit_delta(&self, x: f64, y: f64) {
+        self.camera.lock().update_orbit_delta(
+            &self.state.queue,
+            &self.surface,
+            Vector2::new(x, -y),
+        );
+    }
+
+    /// Turns the view about the centre of the screen while dragging.
+    pub fn update_rotation_delta(&self, x: f64, y: f64) {
+        self.camera.lock().update_rotation_delta(
+            &self.state.queue,
+            &self.surface,
+            Vector2::new(x, -y),
+        );
     }
 
+    /// Ends a drag, letting the camera glide on if it was moving.
     pub fn reset_delta(&self) {
-        self.camera.lock().reset_delta();
+        let now = self.surface.window.now();
+        if self.camera.lock().release(now) {
+            self.surface.window.request_redraw();
+        }
     }
 
-    pub fn set_tiling(&mut self, tiling: TilingGenerator, depth: usize) {
-        self.tiling = tiling;
-        self.mesh = Mesh::new(&self.state.device, self.tiling.generate(COLORS, depth));
+    /// Sets how quickly the camera stops gliding after a drag, as the rate
+    /// at which its speed decays per second.
+    pub fn set_friction(&self, friction: f64) {
+        self.camera.lock().set_friction(friction);
+    }
+
+    /// Flies along the geodesic from the centre of the view to the point
+    /// under the pixel at (`x`, `y`) over `duration` milliseconds, until that
+    /// point is at the centre.
+    pub fn recentre_at(&self, x: f64, y: f64, duration: f64) {
+        let now = self.surface.window.now();
+        let mut camera = self.camera.lock();
+        let Some(target) = camera.unproject(&self.surface, Vector2::new(x, y)) else {
+            return;
+        };
+        camera.fly_to(&self.state.queue, target, now, duration);
+        self.surface.window.request_redraw();
+    }
+
+    /// Flies along the geodesic to the point at (`x`, `y`) in the Poincaré
+    /// disk, before the camera's transform, over `duration` milliseconds.
+    pub fn fly_to(&self, x: f64, y: f64, duration: f64) {
+        let p = Vector2::new(x, y);
+        let r2 = p.magnitude2();
+        if r2 >= 1.0 {
+            warn!("({}, {}) is not in the Poincaré disk", x, y);
+            return;
+        }
+        let target = (p * 2.0).extend(1.0 + r2) / (1.0 - r2);
+        let now = self.surface.window.now();
+        self.camera
+            .lock()
+            .fly_to(&self.state.queue, target, now, duration);
+        self.surface.window.request_redraw();
+    }
+
+    /// Turns the view counterclockwise about the centre of the screen by
+    /// `angle` radians.
+    pub fn rotate_by(&self, angle: f64) {
+        self.camera.lock().rotate_by(&self.state.queue, angle);
         self.surface.window.request_redraw();
     }
 
+    /// Sets the angle the view is turned by about the centre of the screen,
+    /// keeping the point at the centre.
+    pub fn set_rotation(&self, angle: f64) {
+        self.camera.lock().set_rotation(&self.state.queue, angle);
+        self.surface.window.request_redraw();
+    }
+
+    /// Returns the isometry taking the plane to the view, as a 3×3 Lorentz
+    /// matrix in column-major order. When drawing the mesh, this is composed
+    /// with the symmetries of the tiling that keep the camera over the tile
+    /// around the origin.
+    pub fn isometry(&self) -> Vec<f64> {
+        let m: [[f64; 3]; 3] = self.camera.lock().isometry().into();
+        m.into_iter().flatten().collect()
+    }
+
+    /// Moves the camera to an arbitrary orientation-preserving isometry, given
+    /// as in [`App::isometry`], throwing an error if it isn't one.
+    pub fn set_isometry(&self, matrix: &[f64]) -> Result<(), JsError> {
+        Ok(self.try_set_isometry(matrix)?)
+    }
+
+    /// Pans the Euclidean view of the projected plane while dragging, which
+    /// is useful for the unbounded upper half-plane.
+    pub fn update_view_delta(&self, x: f64, y: f64) {
+        self.camera
+            .lock()
+            .update_view_delta(&self.state.queue, &self.surface, Vector2::new(x, -y));
+    }
+
+    /// Scales the Euclidean view of the projected plane by `factor` about the
+    /// pixel at (`x`, `y`), independently of the camera's position in the
+    /// hyperbolic plane.
+    pub fn zoom_at(&mut self, x: f64, y: f64, factor: f64) {
+        if !factor.is_finite() || factor <= 0.0 {
+            warn!("{} is not a valid zoom factor", factor);
+            return;
+        }
+        self.camera
+            .lock()
+            .zoom_view(&self.state.queue, &self.surface, factor, Vector2::new(x, y));
+        self.update_detail();
+    }
+
+    /// Sets the scale of the Euclidean view to `zoom` times the one suiting
+    /// the projection, about the centre of the screen.
+    pub fn set_zoom(&mut self, zoom: f64) {
+        if !zoom.is_finite() || zoom <= 0.0 {
+            warn!("{} is not a valid zoom", zoom);
+            return;
+        }
+        self.camera.lock().set_zoom(&self.state.queue, zoom);
+        self.surface.window.request_redraw();
+        self.update_detail();
+    }
+
+    /// Returns the scale of the Euclidean view relative to the one suiting
+    /// the projection.
+    pub fn zoom(&self) -> f64 {
+        self.camera.lock().zoom()
+    }
+
+    /// Returns the tile under the pixel at (`x`, `y`) on the canvas, if the
+    /// point there is one of the tiles up to the depth.
+    pub fn pick(&self, x: f64, y: f64) -> Option<Pick> {
+        let point = self
+            .camera
+            .lock()
+            .unproject(&self.surface, Vector2::new(x, y))?;
+        let i = self.generated.pick(point)?;
+        let placed = &self.generated.placed[i];
+        Some(Pick {
+            address: placed.address.clone(),
+            color: self.generated.instances[i].color,
+            point,
+            transform: placed.transform,
+        })
+    }
+
+    /// Highlights the face under the pixel at (`x`, `y`), drawn as a mesh,
+    /// with all of its pieces.
+    pub fn hover(&self, x: f64, y: f64) {
+        let point = self
+            .camera
+            .lock()
+            .unproject(&self.surface, Vector2::new(x, y));
+        self.set_hovered(point);
+    }
+
+    /// Stops highlighting the face last hovered, once the pointer leaves.
+    pub fn clear_hover(&self) {
+        self.set_hovered(None);
+    }
+
+    /// Selects the face under the pixel at (`x`, `y`), or deselects it if it
+    /// already was.
+    pub fn toggle_selection(&self, x: f64, y: f64) {
+        if !self.can_select() {
+            return;
+        }
+        let point = self
+            .camera
+            .lock()
+            .unproject(&self.surface, Vector2::new(x, y));
+        if let Some(point) = point {
+            let mut highlight = self.highlight.lock();
+            highlight.toggle(&self.generated, point);
+            self.update_flags(&highlight);
+            self.surface.window.request_redraw();
+        }
+    }
+
+    /// Whether faces can be hovered and selected, which they can't while the
+    /// tiling is folded per pixel, as folding doesn't tell the faces apart.
+    /// The selection is kept, and shown again once the mesh is drawn.
+    pub fn can_select(&self) -> bool {
+        self.folded.is_none()
+    }
+
+    pub fn clear_selection(&self) {
+        let mut highlight = self.highlight.lock();
+        highlight.clear_selection();
+        self.update_flags(&highlight);
+        self.surface.window.request_redraw();
+    }
+
+    /// Returns the tiles up to the depth as an SVG image of the view, leaving
+    /// out tiles smaller than `min_size` pixels across.
+    pub fn export_svg(&self, min_size: f64) -> Result<String, JsError> {
+        Ok(self.try_export_svg(min_size)?)
+    }
+
+    pub fn set_tiling(&mut self, tiling: TilingGenerator, depth: usize) {
+        self.highlight.lock().clear_selection();
+        self.dual = tiling.try_dual().ok();
+        self.tiling = Box::new(tiling);
+        self.colors = COLORS;
+        self.recentred = Matrix3::one();
+        self.set_depth(depth);
+    }
+
+    pub fn set_triangle_tiling(&mut self, tiling: TriangleTiling, depth: usize) {
+        self.highlight.lock().clear_selection();
+        self.dual = None;
+        self.tiling = Box::new(tiling);
+        self.colors = PARITY_COLORS;
+        self.recentred = Matrix3::one();
+        self.set_depth(depth);
+    }
+
     pub fn set_depth(&mut self, depth: usize) {
-        self.mesh = Mesh::new(&self.state.device, self.tiling.generate(COLORS, depth));
+        self.depth = depth;
+        let device = &self.state.device;
+        let layouts = &self.pipeline.layout;
+        let layout = &layouts.palette;
+        self.folded = match self.renderer {
+            Renderer::Fold => {
+                let mut folded = self.tiling.domain().and_then(|domain| {
+                    FoldedTiling::new(device, layout, &layouts.domain, &domain, self.colors)
+                });
+                if let Some(folded) = &mut folded {
+                    folded.set_origin(&self.state.queue, self.recentred);
+                }
+                if folded.is_none() {
+                    warn!("this tiling cannot be drawn per pixel, drawing its mesh instead");
+                }
+                folded
+            }
+            Renderer::Mesh | Renderer::Overlay => None,
+        };
+        self.generated = self.tiling.generate(self.colors, depth);
+        self.mesh = match self.folded {
+            Some(_) => None,
+            None => Some(Mesh::new(device, layout, &self.generated, self.detail)),
+        };
+        {
+            let mut highlight = self.highlight.lock();
+            highlight.repick(&self.generated);
+            self.update_flags(&highlight);
+        }
+        self.dual_mesh = match &self.dual {
+            Some(dual) if self.overlay => Some(Mesh::new(
+                device,
+                layout,
+                &dual.generate_edges(DUAL_COLOR, depth),
+                self.detail,
+            )),
+            _ => None,
+        };
+        // The mesh only reaches `depth` tiles away, so the camera is kept over
+        // the tile around the origin. So it is when drawing per pixel, where
+        // the camera's transform would otherwise grow until the shader runs
+        // out of precision.
+        self.camera
+            .lock()
+            .set_neighbours(&self.state.queue, Some(self.tiling.neighbours()));
         self.surface.window.request_redraw();
     }
 
-    pub fn set_projection(&mut self, name: &str) {
-        let projection = match name {
-            "poincare" => Projection::Poincare,
-            "klein" => Projection::Klein,
-            "hyperboloid" => Projection::Hyperboloid,
+    /// Draws the edges of the dual tiling over the tiling, when it has one.
+    pub fn set_overlay(&mut self, overlay: bool) {
+        self.overlay = overlay;
+        self.set_depth(self.depth);
+    }
+
+    /// Shows the Poincaré disk and the Klein plane in the 3D view of the
+    /// hyperboloid, with the rays projecting the sheet onto them.
+    pub fn set_models(&mut self, models: bool) {
+        self.models = models;
+        self.surface.window.request_redraw();
+    }
+
+    /// Switches between drawing the tiling as a mesh, `"mesh"`, and folding
+    /// every pixel back into its domain, `"fold"`, which needs no depth but
+    /// cannot highlight faces, as [`App::can_select`] tells.
+    pub fn set_renderer(&mut self, name: &str) {
+        self.renderer = match name {
+            "mesh" => Renderer::Mesh,
+            "fold" => Renderer::Fold,
             _ => {
-                warn!("{} is not a valid projection", name);
+                warn!("{} is not a valid renderer", name);
                 return;
             }
         };
-        self.pipeline = Pipeline::with_layout(
-            &self.state.device,
-            self.pipeline.layout.clone(),
-            projection,
-            self.surface.swapchain_format,
-        );
+        self.set_depth(self.depth);
+    }
+
+    /// Starts the action of the key named `key`, as in `KeyboardEvent.key`,
+    /// until it is released, returning whether the key has one.
+    pub fn key_down(&self, key: &str) -> bool {
+        let Some(action) = Action::from_key(key) else {
+            return false;
+        };
+        if self.input.lock().press(action) {
+            self.surface.window.request_redraw();
+        }
+        true
+    }
+
+    pub fn key_up(&self, key: &str) {
+        if let Some(action) = Action::from_key(key) {
+            self.input.lock().release(action);
+        }
+    }
+
+    /// Releases every key, for when the page loses focus.
+    pub fn release_keys(&self) {
+        self.input.lock().release_all();
+    }
+
+    /// Sets the position of the gamepad sticks, each axis from -1 to 1: the
+    /// direction to move in as (`x`, `y`), with y pointing forward, the
+    /// counterclockwise `turn` and the `zoom`, positive to zoom in.
+    pub fn set_gamepad(&self, x: f64, y: f64, turn: f64, zoom: f64) {
+        let mut input = self.input.lock();
+        input.set_sticks(Vector2::new(x, y), turn, zoom);
+        if input.is_active() {
+            self.surface.window.request_redraw();
+        }
+    }
+
+    /// Switches to the projection called `name` at once.
+    pub fn set_projection(&mut self, name: &str) {
+        let Some(projection) = Projection::from_name(name) else {
+            warn!("{} is not a valid projection", name);
+            return;
+        };
+        self.camera
+            .lock()
+            .set_projection(&self.state.queue, projection);
         self.surface.window.request_redraw();
+        self.update_detail();
     }
 
-    pub fn draw(&self) {
+    /// Blends into the projection called `name` over `duration`
+    /// milliseconds.
+    pub fn animate_projection(&mut self, name: &str, duration: f64) {
+        let Some(projection) = Projection::from_name(name) else {
+            warn!("{} is not a valid projection", name);
+            return;
+        };
+        let now = self.surface.window.now();
+        self.camera
+            .lock()
+            .animate_projection(&self.state.queue, projection, now, duration);
+        self.surface.window.request_redraw();
+        self.update_detail();
+    }
+
+    pub fn draw(&mut self) {
+        self.step_input();
         let frame = self
             .surface
             .get_current_texture()
@@ -219,7 +971,26 @@ impl App {
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
         {
-            let camera = self.camera.lock();
+            let depth = self.surface.depth();
+            let mut camera = self.camera.lock();
+            if camera.animate(&self.state.queue, self.surface.window.now()) {
+                self.surface.window.request_redraw();
+            }
+            let recentred = camera.take_recentred();
+            if recentred != Matrix3::one() {
+                self.recentred = lorentz_orthonormalize(self.recentred * recentred);
+                if let Some(folded) = &mut self.folded {
+                    folded.set_origin(&self.state.queue, self.recentred);
+                }
+                if self.tiling.recentre(&mut self.generated, recentred) {
+                    if let Some(mesh) = &self.mesh {
+                        mesh.set_instances(&self.state.queue, &self.generated.instances);
+                    }
+                }
+                let mut highlight = self.highlight.lock();
+                highlight.recentre(&self.generated, recentred);
+                self.update_flags(&highlight);
+            }
 
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
@@ -231,15 +1002,120 @@ impl App {
                         store: true,
                     },
                 })],
-                depth_stencil_attachment: None,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
             });
-            rpass.set_pipeline(&self.pipeline);
             rpass.set_bind_group(0, &camera.bind_group, &[]);
-            rpass.set_vertex_buffer(0, self.mesh.vertex.slice(..));
-            rpass.set_index_buffer(self.mesh.index.slice(..), wgpu::IndexFormat::Uint32);
-            rpass.draw_indexed(0..(self.mesh.index.size() / 4) as _, 0, 0..1);
+            if let Some(folded) = &self.folded {
+                rpass.set_pipeline(&self.fold_pipeline);
+                folded.draw(&mut rpass);
+            }
+            let models = self.models && camera.is_3d();
+            rpass.set_pipeline(&self.pipeline);
+            for (layer, bind_group) in self.layers.iter().enumerate() {
+                if layer != Layer::Hyperboloid as usize && !models {
+                    break;
+                }
+                rpass.set_bind_group(2, bind_group, &[]);
+                if let Some(mesh) = &self.mesh {
+                    mesh.draw(&mut rpass);
+                }
+                if let Some(mesh) = &self.dual_mesh {
+                    mesh.draw(&mut rpass);
+                }
+            }
+            if models {
+                rpass.set_pipeline(&self.overlay_pipeline);
+                self.model_lines.draw(&mut rpass);
+            }
         }
         self.state.queue.submit(Some(encoder.finish()));
         frame.present();
     }
 }
+impl App {
+    fn set_hovered(&self, point: Option<Vector3<f64>>) {
+        if !self.can_select() {
+            return;
+        }
+        let mut highlight = self.highlight.lock();
+        if highlight.hover(&self.generated, point) {
+            self.update_flags(&highlight);
+            self.surface.window.request_redraw();
+        }
+    }
+
+    /// Moves the camera by the keys and sticks held since the last frame,
+    /// asking for another frame while they are.
+    fn step_input(&mut self) {
+        let now = self.surface.window.now();
+        let Some(step) = self.input.lock().advance(now) else {
+            return;
+        };
+        let centre = self.surface.size() / 2.0;
+        {
+            let mut camera = self.camera.lock();
+            let queue = &self.state.queue;
+            camera.move_by(queue, step.offset);
+            camera.rotate_by(queue, step.angle);
+            if step.zoom != 1.0 {
+                camera.zoom_view(queue, &self.surface, step.zoom, centre);
+            }
+        }
+        if step.zoom != 1.0 {
+            self.update_detail();
+        }
+        self.surface.window.request_redraw();
+    }
+
+    /// Subdivides the meshes further when zoomed in, so that the curved sides
+    /// of the tiles stay smooth, and back when zoomed out.
+    fn update_detail(&mut self) {
+        let zoom = self.camera.lock().zoom();
+        // The gap between a side and its chords grows with the zoom and the
+        // square of their length, which each level of detail halves.
+        let detail = (zoom.log2() / 2.0).ceil().clamp(0.0, MAX_DETAIL as f64) as u32;
+        if detail != self.detail {
+            self.detail = detail;
+            let device = &self.state.device;
+            for mesh in self.mesh.iter_mut().chain(&mut self.dual_mesh) {
+                mesh.set_detail(device, detail);
+            }
+            self.surface.window.request_redraw();
+        }
+    }
+
+    /// Writes the flags of the tiles picked in `highlight` for the mesh.
+    fn update_flags(&self, highlight: &Highlight) {
+        if let Some(mesh) = &self.mesh {
+            mesh.set_flags(&self.state.queue, &highlight.flags(&self.generated));
+        }
+    }
+
+    pub fn try_export_svg(&self, min_size: f64) -> Result<String, SvgError> {
+        let camera = self.camera.lock();
+        let (centre, scale) = camera.view();
+        let view = SvgView {
+            projection: camera.projection(),
+            isometry: camera.isometry(),
+            centre,
+            scale,
+            size: self.surface.size(),
+            min_size,
+        };
+        svg::export(&*self.tiling, self.colors, self.depth, &view)
+    }
+
+    pub fn try_set_isometry(&self, matrix: &[f64]) -> Result<(), IsometryError> {
+        let m = camera::matrix_from_slice(matrix)?;
+        self.camera.lock().try_set_isometry(&self.state.queue, m)?;
+        self.surface.window.request_redraw();
+        Ok(())
+    }
+}
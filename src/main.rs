@@ -1,7 +1,45 @@
 /// Re-export [`App`] so that wasm-bindgen can find the bindings in the library.
+#[cfg(target_arch = "wasm32")]
 pub use hyperbolic::App;
 
+#[cfg(target_arch = "wasm32")]
 fn main() {
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
     console_log::init().expect("could not initialize logger");
 }
+
+/// Opens a desktop window showing the tiling {P,Q}, where P or Q may be `inf`
+/// for ∞, up to the given depth, as in `cargo run-host -- 7 3`.
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    use hyperbolic::tiling::{Order, TilingGenerator};
+
+    const USAGE: &str = "usage: hyperbolic [P Q [DEPTH]]";
+    env_logger::init();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let parse = |p: &str, q: &str, depth: &str| {
+        Some((
+            p.parse::<Order>().ok()?.0,
+            q.parse::<Order>().ok()?.0,
+            depth.parse::<usize>().ok()?,
+        ))
+    };
+    let parsed = match &args[..] {
+        [] => Some((Some(4), Some(5), 4)),
+        [p, q] => parse(p, q, "4"),
+        [p, q, depth] => parse(p, q, depth),
+        _ => None,
+    };
+    let Some((p, q, depth)) = parsed else {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    };
+    let tiling = match TilingGenerator::try_from_schlafli(p, q) {
+        Ok(tiling) => tiling,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    hyperbolic::native::run(tiling, depth);
+}
@@ -0,0 +1,194 @@
+//! Overlay of the Poincaré disk and the Klein plane on the 3D view of the
+//! hyperboloid, showing how the models arise by projecting the sheet.
+//!
+//! The Poincaré disk lies in the plane z = 0 and is projected from the pole
+//! (0, 0, -1), while the Klein plane touches the sheet at z = 1 and is
+//! projected from the origin.
+use std::f64::consts::TAU;
+use std::ops::Deref;
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::Vector3;
+use wgpu::{util::DeviceExt, Device};
+
+use crate::Color;
+
+/// Surface the tiling is drawn on in the 3D view. The order must match the
+/// numbering in `mesh.wgsl`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Layer {
+    Hyperboloid,
+    Poincare,
+    Klein,
+}
+impl Layer {
+    pub const ALL: [Layer; 3] = [Layer::Hyperboloid, Layer::Poincare, Layer::Klein];
+}
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct LayerUniform {
+    surface: u32,
+    _padding: [u32; 3],
+}
+
+pub struct LayerBindGroupLayout {
+    inner: wgpu::BindGroupLayout,
+}
+impl LayerBindGroupLayout {
+    pub fn new(device: &Device) -> Self {
+        LayerBindGroupLayout {
+            inner: device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("layer bind_group_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            }),
+        }
+    }
+}
+impl Deref for LayerBindGroupLayout {
+    type Target = wgpu::BindGroupLayout;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+pub struct LayerBindGroup {
+    bind_group: wgpu::BindGroup,
+}
+impl LayerBindGroup {
+    pub fn new(device: &Device, layout: &LayerBindGroupLayout, layer: Layer) -> Self {
+        let uniform = LayerUniform {
+            surface: layer as u32,
+            _padding: [0; 3],
+        };
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            usage: wgpu::BufferUsages::UNIFORM,
+            contents: bytemuck::bytes_of(&uniform),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        LayerBindGroup { bind_group }
+    }
+}
+impl Deref for LayerBindGroup {
+    type Target = wgpu::BindGroup;
+
+    fn deref(&self) -> &Self::Target {
+        &self.bind_group
+    }
+}
+
+/// End of a line segment of the overlay, in the space around the sheet.
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct LineVertex {
+    pub pos: [f32; 3],
+    pub color: [f32; 3],
+}
+impl LineVertex {
+    pub const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Self>() as _,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3],
+    };
+}
+
+const POINCARE_COLOR: Color = Color {
+    r: 214,
+    g: 39,
+    b: 40,
+};
+const KLEIN_COLOR: Color = Color {
+    r: 31,
+    g: 119,
+    b: 180,
+};
+
+/// Segments in the rim of each model, and directions and distances of the
+/// points of the sheet whose projection rays are drawn.
+const RIM_SEGMENTS: usize = 64;
+const RAY_DIRECTIONS: usize = 8;
+const RAY_DISTANCES: [f64; 2] = [1.0, 2.0];
+
+/// Rims of the Poincaré disk and the Klein plane, and rays from their poles
+/// to points of the sheet through their images in the models.
+pub fn lines() -> Vec<LineVertex> {
+    let mut lines = Vec::new();
+    let mut push = |from: Vector3<f64>, to: Vector3<f64>, color: Color| {
+        for v in [from, to] {
+            lines.push(LineVertex {
+                pos: v.cast::<f32>().unwrap().into(),
+                color: color.into(),
+            });
+        }
+    };
+
+    let rim = |k: usize, z: f64| {
+        let (s, c) = (TAU * k as f64 / RIM_SEGMENTS as f64).sin_cos();
+        Vector3::new(c, s, z)
+    };
+    for k in 0..RIM_SEGMENTS {
+        push(rim(k, 0.0), rim(k + 1, 0.0), POINCARE_COLOR);
+        push(rim(k, 1.0), rim(k + 1, 1.0), KLEIN_COLOR);
+    }
+
+    let poincare_pole = Vector3::new(0.0, 0.0, -1.0);
+    let klein_pole = Vector3::new(0.0, 0.0, 0.0);
+    for k in 0..RAY_DIRECTIONS {
+        let (s, c) = (TAU * k as f64 / RAY_DIRECTIONS as f64).sin_cos();
+        for d in RAY_DISTANCES {
+            let v = Vector3::new(d.sinh() * c, d.sinh() * s, d.cosh());
+            push(poincare_pole, v, POINCARE_COLOR);
+            push(klein_pole, v, KLEIN_COLOR);
+        }
+    }
+    // Mark the poles with short crosses.
+    for (pole, color) in [(poincare_pole, POINCARE_COLOR), (klein_pole, KLEIN_COLOR)] {
+        for axis in [Vector3::unit_x(), Vector3::unit_y()] {
+            push(pole - axis * 0.05, pole + axis * 0.05, color);
+        }
+    }
+    lines
+}
+
+/// Lines of the overlay uploaded for drawing.
+pub struct ModelLines {
+    buffer: wgpu::Buffer,
+    count: u32,
+}
+impl ModelLines {
+    pub fn new(device: &Device) -> Self {
+        let lines = lines();
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            usage: wgpu::BufferUsages::VERTEX,
+            contents: bytemuck::cast_slice(&lines),
+        });
+        ModelLines {
+            buffer,
+            count: lines.len() as u32,
+        }
+    }
+
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        rpass.set_vertex_buffer(0, self.buffer.slice(..));
+        rpass.draw(0..self.count, 0..1);
+    }
+}
@@ -0,0 +1,253 @@
+//! Desktop backend, drawing the app into a winit window and driving it with
+//! the mouse and keyboard as `web/main.js` does in the browser.
+use std::time::Instant;
+
+use cgmath::{MetricSpace, Vector2};
+use raw_window_handle::{
+    HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
+};
+use winit::event::{
+    ElementState, Event, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+    VirtualKeyCode, WindowEvent,
+};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::WindowBuilder;
+
+use crate::tiling::TilingGenerator;
+use crate::window::Window;
+use crate::App;
+
+/// Farthest a press may move, in pixels, and still select the tile under it
+/// instead of dragging.
+const CLICK_SLOP: f64 = 4.0;
+
+/// Longest time between two clicks making a double click, in milliseconds.
+const DOUBLE_CLICK_TIME: f64 = 400.0;
+
+/// Duration of the flight to a double-clicked point, in milliseconds.
+const FLIGHT_DURATION: f64 = 600.0;
+
+/// Pixels a wheel scrolls by per line, as browsers report it.
+const PIXELS_PER_LINE: f64 = 100.0;
+
+pub struct NativeWindow {
+    window: winit::window::Window,
+    start: Instant,
+}
+impl NativeWindow {
+    pub fn new(window: winit::window::Window) -> Self {
+        NativeWindow {
+            window,
+            start: Instant::now(),
+        }
+    }
+}
+
+unsafe impl HasRawWindowHandle for NativeWindow {
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        self.window.raw_window_handle()
+    }
+}
+unsafe impl HasRawDisplayHandle for NativeWindow {
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        self.window.raw_display_handle()
+    }
+}
+impl Window for NativeWindow {
+    fn size(&self) -> Vector2<u32> {
+        let size = self.window.inner_size();
+        Vector2::new(size.width, size.height)
+    }
+
+    fn request_redraw(&self) {
+        self.window.request_redraw();
+    }
+
+    fn now(&self) -> f64 {
+        self.start.elapsed().as_secs_f64() * 1000.0
+    }
+}
+
+/// Name of `key` as in `KeyboardEvent.key`, for the keys moving the camera.
+fn key_name(key: VirtualKeyCode) -> Option<&'static str> {
+    Some(match key {
+        VirtualKeyCode::Up => "ArrowUp",
+        VirtualKeyCode::Down => "ArrowDown",
+        VirtualKeyCode::Left => "ArrowLeft",
+        VirtualKeyCode::Right => "ArrowRight",
+        VirtualKeyCode::W => "w",
+        VirtualKeyCode::A => "a",
+        VirtualKeyCode::S => "s",
+        VirtualKeyCode::D => "d",
+        VirtualKeyCode::Q => "q",
+        VirtualKeyCode::E => "e",
+        VirtualKeyCode::Plus | VirtualKeyCode::Equals | VirtualKeyCode::NumpadAdd => "+",
+        VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => "-",
+        _ => return None,
+    })
+}
+
+/// What the mouse and keyboard were doing as of the last event.
+struct Pointer {
+    position: Vector2<f64>,
+    left: bool,
+    right: bool,
+    modifiers: ModifiersState,
+    /// Where the last press started, while it might still be a click.
+    press: Option<Vector2<f64>>,
+    /// Time of the last click, while the next one might make it double.
+    last_click: Option<f64>,
+    start: Instant,
+}
+impl Pointer {
+    fn new() -> Self {
+        Pointer {
+            position: Vector2::new(0.0, 0.0),
+            left: false,
+            right: false,
+            modifiers: ModifiersState::empty(),
+            press: None,
+            last_click: None,
+            start: Instant::now(),
+        }
+    }
+
+    fn now(&self) -> f64 {
+        self.start.elapsed().as_secs_f64() * 1000.0
+    }
+
+    /// Passes `event` on to `app`, returning how the event loop should go
+    /// on.
+    fn handle(&mut self, app: &mut App, event: WindowEvent) -> ControlFlow {
+        match event {
+            WindowEvent::CloseRequested => return ControlFlow::Exit,
+            // A minimized window has no surface to draw into.
+            WindowEvent::Resized(size)
+            | WindowEvent::ScaleFactorChanged {
+                new_inner_size: &mut size,
+                ..
+            } if size.width > 0 && size.height > 0 => app.resize(size.width, size.height),
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers,
+            WindowEvent::Focused(false) => app.release_keys(),
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => {
+                let Some(name) = key_name(key) else {
+                    return ControlFlow::Wait;
+                };
+                match state {
+                    ElementState::Pressed => {
+                        let m = self.modifiers;
+                        if !(m.ctrl() || m.logo() || m.alt()) {
+                            app.key_down(name);
+                        }
+                    }
+                    ElementState::Released => app.key_up(name),
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.position = Vector2::new(position.x, position.y);
+                let Vector2 { x, y } = self.position;
+                if self.left {
+                    // Shift-dragging turns the view about its centre, and
+                    // alt-dragging pans the view of the projected plane
+                    // instead.
+                    if self.modifiers.shift() {
+                        app.update_rotation_delta(x, y);
+                    } else if self.modifiers.alt() {
+                        app.update_view_delta(x, y);
+                    } else {
+                        app.update_delta(x, y);
+                    }
+                } else if self.right {
+                    app.update_orbit_delta(x, y);
+                }
+                app.hover(x, y);
+            }
+            WindowEvent::CursorLeft { .. } => app.clear_hover(),
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.mouse_input(app, state, button);
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let scroll = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y as f64 * PIXELS_PER_LINE,
+                    MouseScrollDelta::PixelDelta(position) => position.y,
+                };
+                let Vector2 { x, y } = self.position;
+                app.zoom_at(x, y, (scroll / 500.0).exp());
+            }
+            _ => {}
+        }
+        ControlFlow::Wait
+    }
+
+    fn mouse_input(&mut self, app: &mut App, state: ElementState, button: MouseButton) {
+        let Vector2 { x, y } = self.position;
+        match (state, button) {
+            (ElementState::Pressed, MouseButton::Left) => {
+                self.left = true;
+                self.press = (!self.right).then_some(self.position);
+                // Pressing down on its own catches the camera if it is still
+                // gliding.
+                if !self.right && !self.modifiers.shift() && !self.modifiers.alt() {
+                    app.update_delta(x, y);
+                }
+            }
+            (ElementState::Pressed, MouseButton::Right) => {
+                self.right = true;
+                self.press = None;
+            }
+            (ElementState::Released, MouseButton::Left | MouseButton::Right) => {
+                if button == MouseButton::Left {
+                    self.left = false;
+                } else {
+                    self.right = false;
+                }
+                app.reset_delta();
+                let clicked = self
+                    .press
+                    .take()
+                    .is_some_and(|press| press.distance(self.position) < CLICK_SLOP);
+                if clicked {
+                    app.toggle_selection(x, y);
+                    let now = self.now();
+                    match self.last_click {
+                        Some(last) if now - last < DOUBLE_CLICK_TIME => {
+                            app.recentre_at(x, y, FLIGHT_DURATION);
+                            self.last_click = None;
+                        }
+                        _ => self.last_click = Some(now),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Opens a window showing `tiling` up to `depth`, and runs its event loop
+/// until it is closed.
+pub fn run(tiling: TilingGenerator, depth: usize) -> ! {
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title("Hyperbolic space")
+        .build(&event_loop)
+        .expect("failed to open a window");
+    let mut app = pollster::block_on(App::with_window(tiling, NativeWindow::new(window)));
+    app.set_depth(depth);
+    let mut pointer = Pointer::new();
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Wait;
+        match event {
+            Event::RedrawRequested(_) => app.draw(),
+            Event::WindowEvent { event, .. } => *control_flow = pointer.handle(&mut app, event),
+            _ => {}
+        }
+    })
+}
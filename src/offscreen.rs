@@ -0,0 +1,277 @@
+//! Drawing a tiling into a texture instead of a window, and reading the
+//! pixels back as a PNG image, for rendering figures without a display.
+use std::fmt;
+use std::io::Write;
+use std::num::NonZeroU32;
+
+use cgmath::{Matrix3, Vector2};
+
+use crate::camera::{Camera, IsometryError};
+use crate::models::{Layer, LayerBindGroup};
+use crate::pipeline::{Pipeline, Projection, Renderer};
+use crate::surface::{create_depth, State};
+#[cfg(test)]
+use crate::tiling::TilingGenerator;
+use crate::tiling::{Tiling, TriangleTiling};
+use crate::{Color, Mesh, COLORS, PARITY_COLORS};
+
+/// Format of the image drawn into, matching what browsers draw the canvas
+/// in.
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Why the offscreen renderer cannot be set up.
+#[derive(Debug)]
+pub enum OffscreenError {
+    /// Neither a GPU nor a software adapter was found.
+    NoAdapter,
+    Device(wgpu::RequestDeviceError),
+}
+impl fmt::Display for OffscreenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OffscreenError::NoAdapter => write!(f, "no graphics adapter was found"),
+            OffscreenError::Device(err) => write!(f, "failed to create device: {}", err),
+        }
+    }
+}
+impl std::error::Error for OffscreenError {}
+impl From<wgpu::RequestDeviceError> for OffscreenError {
+    fn from(err: wgpu::RequestDeviceError) -> Self {
+        OffscreenError::Device(err)
+    }
+}
+
+/// Renderer drawing the mesh of a tiling into an image of a fixed size.
+pub struct Offscreen {
+    state: State,
+    pipeline: Pipeline,
+    camera: Camera,
+    layer: LayerBindGroup,
+    mesh: Option<Mesh>,
+    size: Vector2<u32>,
+    target: wgpu::Texture,
+    depth: wgpu::TextureView,
+}
+impl Offscreen {
+    /// Sets up drawing images of `size` pixels, on the GPU if there is one
+    /// and in software otherwise.
+    pub async fn new(size: Vector2<u32>) -> Result<Self, OffscreenError> {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let mut adapter = None;
+        for force_fallback_adapter in [false, true] {
+            adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    force_fallback_adapter,
+                    compatible_surface: None,
+                })
+                .await;
+            if adapter.is_some() {
+                break;
+            }
+        }
+        let adapter = adapter.ok_or(OffscreenError::NoAdapter)?;
+        let state = State::new(&adapter).await?;
+
+        let pipeline = Pipeline::new(&state.device, Renderer::Mesh, FORMAT);
+        let camera = Camera::new(
+            &state.device,
+            &pipeline.layout.camera,
+            size.x as f64 / size.y as f64,
+        );
+        let layer = LayerBindGroup::new(&state.device, &pipeline.layout.layer, Layer::Hyperboloid);
+        let target = state.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen texture"),
+            size: wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        });
+        let depth = create_depth(&state.device, size);
+        Ok(Offscreen {
+            state,
+            pipeline,
+            camera,
+            layer,
+            mesh: None,
+            size,
+            target,
+            depth,
+        })
+    }
+
+    /// Draws the tiles of `tiling` up to `depth`, coloured by fragment as in
+    /// [`App::set_tiling`](crate::App::set_tiling).
+    pub fn set_tiling(&mut self, tiling: &dyn Tiling, depth: usize) {
+        self.set_mesh(tiling, COLORS, depth);
+    }
+
+    /// Draws the triangles of `tiling` up to `depth`, coloured by parity as
+    /// in [`App::set_triangle_tiling`](crate::App::set_triangle_tiling).
+    pub fn set_triangle_tiling(&mut self, tiling: &TriangleTiling, depth: usize) {
+        self.set_mesh(tiling, PARITY_COLORS, depth);
+    }
+
+    fn set_mesh(&mut self, tiling: &dyn Tiling, colors: &[Color], depth: usize) {
+        let generated = tiling.generate(colors, depth);
+        self.mesh = Some(Mesh::new(
+            &self.state.device,
+            &self.pipeline.layout.palette,
+            &generated,
+            0,
+        ));
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.camera.set_projection(&self.state.queue, projection);
+    }
+
+    /// Moves the camera to the isometry `m`, as [`Camera::try_set_isometry`].
+    /// The camera isn't kept over the tile around the origin, so that the
+    /// image shows the tiling as placed by `m`.
+    pub fn set_isometry(&mut self, m: Matrix3<f64>) -> Result<(), IsometryError> {
+        self.camera.try_set_isometry(&self.state.queue, m)
+    }
+
+    /// Draws the image and reads it back, as rows of RGBA pixels from the
+    /// top.
+    pub fn render(&self) -> Vec<u8> {
+        let device = &self.state.device;
+        let view = self
+            .target
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.camera.bind_group, &[]);
+            rpass.set_bind_group(2, &self.layer, &[]);
+            if let Some(mesh) = &self.mesh {
+                mesh.draw(&mut rpass);
+            }
+        }
+
+        // Rows are copied out with their lengths padded to the alignment.
+        let row = 4 * self.size.x;
+        let padded_row = wgpu::util::align_to(row, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("offscreen read-back"),
+            size: padded_row as u64 * self.size.y as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            self.target.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: self.size.x,
+                height: self.size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.state.queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("failed to read back the image");
+        });
+        device.poll(wgpu::Maintain::Wait);
+        let pixels = slice
+            .get_mapped_range()
+            .chunks_exact(padded_row as usize)
+            .flat_map(|padded| &padded[..row as usize])
+            .copied()
+            .collect();
+        buffer.unmap();
+        pixels
+    }
+
+    /// Draws the image and writes it to `w` as a PNG file.
+    pub fn write_png<W: Write>(&self, w: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(w, self.size.x, self.size.y);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.render())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sets up the renderer, which CI runners without a graphics adapter,
+    /// not even a software one, cannot do.
+    fn offscreen(size: Vector2<u32>) -> Offscreen {
+        pollster::block_on(Offscreen::new(size)).expect("the renderer cannot be set up")
+    }
+
+    #[test]
+    #[ignore = "needs a graphics adapter"]
+    fn renders_image_of_size() {
+        // A width whose rows need padding when read back.
+        let size = Vector2::new(100, 30);
+        let mut offscreen = offscreen(size);
+        offscreen.set_tiling(
+            &TilingGenerator::try_from_schlafli(Some(4), Some(5)).unwrap(),
+            2,
+        );
+        let pixels = offscreen.render();
+        assert_eq!(pixels.len(), 4 * 100 * 30);
+        // The corners lie outside the disk, and the centre in the red tile
+        // around the origin.
+        let pixel = |x: usize, y: usize| &pixels[4 * (100 * y + x)..][..4];
+        assert_eq!(pixel(0, 0), [255, 255, 255, 255]);
+        assert_eq!(pixel(99, 29), [255, 255, 255, 255]);
+        assert_eq!(pixel(50, 15), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    #[ignore = "needs a graphics adapter"]
+    fn colours_triangles_by_parity() {
+        let mut offscreen = offscreen(Vector2::new(64, 64));
+        offscreen.set_triangle_tiling(&TriangleTiling::try_new(2, 3, 7).unwrap(), 4);
+        let pixels = offscreen.render();
+        // Every pixel of the disk lies in a dark or a light grey triangle,
+        // rather than in the colours of the fragments.
+        let inside: Vec<_> = pixels
+            .chunks_exact(4)
+            .filter(|&pixel| pixel != [255, 255, 255, 255])
+            .collect();
+        assert!(inside.iter().all(|p| p[0] == p[1] && p[1] == p[2]));
+        assert!(inside.iter().any(|p| p[0] < 128));
+        assert!(inside.iter().any(|p| p[0] >= 128));
+    }
+}
@@ -0,0 +1,85 @@
+use std::ops::Deref;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{util::DeviceExt, Device};
+
+use crate::Color;
+
+/// Number of colours in a palette, which must match the size of the array in
+/// the shaders.
+pub const PALETTE_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, Zeroable, Pod)]
+#[repr(C)]
+pub struct PaletteUniform {
+    colors: [[f32; 4]; PALETTE_SIZE],
+}
+impl PaletteUniform {
+    pub fn new(colors: &[Color]) -> Self {
+        let mut uniform = PaletteUniform::zeroed();
+        for (slot, &color) in uniform.colors.iter_mut().zip(colors) {
+            let [r, g, b]: [f32; 3] = color.into();
+            *slot = [r, g, b, 1.0];
+        }
+        uniform
+    }
+}
+
+pub struct PaletteBindGroupLayout {
+    inner: wgpu::BindGroupLayout,
+}
+impl PaletteBindGroupLayout {
+    pub fn new(device: &Device) -> Self {
+        PaletteBindGroupLayout {
+            inner: device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("palette bind_group_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            }),
+        }
+    }
+}
+impl Deref for PaletteBindGroupLayout {
+    type Target = wgpu::BindGroupLayout;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+pub struct PaletteBindGroup {
+    bind_group: wgpu::BindGroup,
+}
+impl PaletteBindGroup {
+    pub fn new(device: &Device, layout: &PaletteBindGroupLayout, colors: &[Color]) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            usage: wgpu::BufferUsages::UNIFORM,
+            contents: bytemuck::bytes_of(&PaletteUniform::new(colors)),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        PaletteBindGroup { bind_group }
+    }
+}
+impl Deref for PaletteBindGroup {
+    type Target = wgpu::BindGroup;
+
+    fn deref(&self) -> &Self::Target {
+        &self.bind_group
+    }
+}
@@ -0,0 +1,136 @@
+struct VertexOutput {
+    @location(0)
+    screen: vec2<f32>,
+    @builtin(position)
+    pos: vec4<f32>,
+}
+
+// The size must match `PALETTE_SIZE`.
+struct Palette {
+    colors: array<vec4<f32>, 16>,
+}
+
+// The size must match `DOMAIN_SIDES`.
+struct Domain {
+    sides: array<vec4<f32>, 16>,
+    // Transform taking the tiles around the origin to those they are
+    // coloured as.
+    origin: mat3x3<f32>,
+    centre: vec4<f32>,
+    count: u32,
+    parity: u32,
+    slots: u32,
+}
+
+@group(1) @binding(0)
+var<uniform> palette: Palette;
+
+@group(2) @binding(0)
+var<uniform> domain: Domain;
+
+// Points needing more reflections than this are left where they end up,
+// which only happens within a pixel of the rim.
+let MAX_FOLDS: u32 = 256u;
+
+// Margin by which the centre of a tile must lie outside a side to be
+// reflected, so that centres on the line through a side are not.
+let CENTRE_MARGIN: f32 = 0.001;
+
+struct Fold {
+    steps: u32,
+    // Product of the reflections, taking the domain to the tile.
+    transform: mat3x3<f32>,
+}
+
+fn reflection(n: vec3<f32>) -> mat3x3<f32> {
+    return mat3x3<f32>(
+        vec3<f32>(1.0, 0.0, 0.0) - 2.0 * n.x * n,
+        vec3<f32>(0.0, 1.0, 0.0) - 2.0 * n.y * n,
+        vec3<f32>(0.0, 0.0, 1.0) + 2.0 * n.z * n,
+    );
+}
+
+// Reflects `v` across the sides of the domain it lies beyond by more
+// than `margin` until it lies within.
+fn fold(v: vec3<f32>, margin: f32) -> Fold {
+    var v = v;
+    var result: Fold;
+    result.steps = 0u;
+    result.transform = mat3x3<f32>(
+        vec3<f32>(1.0, 0.0, 0.0),
+        vec3<f32>(0.0, 1.0, 0.0),
+        vec3<f32>(0.0, 0.0, 1.0),
+    );
+    loop {
+        var reflected = false;
+        for (var i = 0u; i < domain.count; i++) {
+            let n = domain.sides[i].xyz;
+            let d = minkowski(n, v);
+            if d < -margin && result.steps < MAX_FOLDS {
+                v = v - 2.0 * d * n;
+                // Keep the point on the hyperboloid despite rounding.
+                v = v / sqrt(-minkowski(v, v));
+                result.transform = result.transform * reflection(n);
+                result.steps += 1u;
+                reflected = true;
+            }
+        }
+        if !reflected {
+            break;
+        }
+    }
+    return result;
+}
+
+// Colour of the tile containing `v`, given on the hyperboloid as seen
+// by the camera.
+fn shade(v: vec3<f32>) -> vec4<f32> {
+    // The inverse of a Lorentz transformation is its transpose conjugated by
+    // the metric.
+    let t = camera.transform;
+    let inverse = mat3x3<f32>(
+        vec3<f32>(t[0].x, t[1].x, -t[2].x),
+        vec3<f32>(t[0].y, t[1].y, -t[2].y),
+        vec3<f32>(-t[0].z, -t[1].z, t[2].z),
+    );
+    let tile = fold(inverse * v, 0.0);
+    var slot: u32;
+    if domain.parity != 0u {
+        slot = tile.steps % 2u;
+    } else {
+        // The count runs from the tile the one around the origin was first
+        // drawn as, wherever the camera has since been moved back to.
+        let centre = domain.origin * tile.transform * domain.centre.xyz;
+        slot = fold(centre, CENTRE_MARGIN).steps;
+    }
+    return vec4<f32>(palette.colors[slot % domain.slots].rgb, 1.0);
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // A triangle whose inscribed square is the screen.
+    let screen = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u)) * 2.0 - 1.0;
+    var output: VertexOutput;
+    output.pos = vec4<f32>(screen, 0.0, 1.0);
+    output.screen = screen;
+    return output;
+}
+
+struct FragmentOutput {
+    @location(0)
+    color: vec4<f32>,
+    @builtin(frag_depth)
+    depth: f32,
+}
+
+@fragment
+fn fs_main(@location(0) screen: vec2<f32>) -> FragmentOutput {
+    let v = unprojected(screen);
+    if v.z == 0.0 {
+        discard;
+    }
+    var output: FragmentOutput;
+    output.color = shade(v.xyz);
+    output.depth = v.w;
+    return output;
+}
@@ -0,0 +1,85 @@
+struct VertexOutput {
+    @location(0)
+    color: vec3<f32>,
+    @location(1) @interpolate(flat)
+    flags: u32,
+    @builtin(position)
+    pos: vec4<f32>,
+}
+
+// Flags of an instance, as in `InstanceFlags`.
+let HOVERED: u32 = 1u;
+let SELECTED: u32 = 2u;
+
+// Colours tiles are blended towards when highlighted.
+let HOVER_COLOR: vec3<f32> = vec3<f32>(1.0, 1.0, 1.0);
+let SELECTION_COLOR: vec3<f32> = vec3<f32>(1.0, 0.55, 0.1);
+
+// The size must match `PALETTE_SIZE`.
+struct Palette {
+    colors: array<vec4<f32>, 16>,
+}
+
+// Where the tiling is drawn in the 3D view, numbered as in `Layer`.
+struct Layer {
+    surface: u32,
+}
+
+@group(1) @binding(0)
+var<uniform> palette: Palette;
+
+@group(2) @binding(0)
+var<uniform> layer: Layer;
+
+// Point of the layer showing `v` in the 3D view: the hyperboloid itself,
+// the Poincaré disk below it, or the Klein plane touching it.
+fn layer_point(v: vec3<f32>) -> vec3<f32> {
+    switch layer.surface {
+        case 1u: {
+            return vec3<f32>(project_pole(0.5, v), 0.0);
+        }
+        case 2u: {
+            return vec3<f32>(project_pole(0.0, v), 1.0);
+        }
+        default: {
+            return v;
+        }
+    }
+}
+
+@vertex
+fn vs_main(
+    @location(0) pos: vec3<f32>,
+    @location(1) transform_x: vec3<f32>,
+    @location(2) transform_y: vec3<f32>,
+    @location(3) transform_z: vec3<f32>,
+    @location(4) color: u32,
+    @location(5) flags: u32,
+) -> VertexOutput {
+    let transform = mat3x3<f32>(transform_x, transform_y, transform_z);
+    let pos = camera.transform * vec4<f32>(transform * pos, 1.0);
+    var output: VertexOutput;
+    if layer.surface == 0u {
+        output.pos = projected(pos.xyz);
+    } else {
+        output.pos = camera.perspective * vec4<f32>(layer_point(pos.xyz), 1.0);
+    }
+    output.color = palette.colors[color].rgb;
+    output.flags = flags;
+    return output;
+}
+
+@fragment
+fn fs_main(
+    @location(0) color: vec3<f32>,
+    @location(1) @interpolate(flat) flags: u32,
+) -> @location(0) vec4<f32> {
+    var color = color;
+    if (flags & SELECTED) != 0u {
+        color = mix(color, SELECTION_COLOR, 0.6);
+    }
+    if (flags & HOVERED) != 0u {
+        color = mix(color, HOVER_COLOR, 0.3);
+    }
+    return vec4<f32>(color, 1.0);
+}
@@ -1,39 +1,231 @@
-use std::{ops::Deref, sync::Arc};
+use std::f64::consts::PI;
+use std::{borrow::Cow, ops::Deref, sync::Arc};
 
+use cgmath::{InnerSpace, Vector2, Vector3};
 use wgpu::Device;
 
 use crate::camera::CameraBindGroupLayout;
+use crate::domain::DomainBindGroupLayout;
+use crate::models::{LayerBindGroupLayout, LineVertex};
+use crate::palette::PaletteBindGroupLayout;
+use crate::surface::DEPTH_FORMAT;
 
+/// Projection of the hyperboloid onto the screen. The order must match the
+/// numbering in `projection.wgsl`.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Projection {
     Poincare,
     Klein,
     Hyperboloid,
+    /// Upper half-plane, whose boundary point at infinity is the ideal point
+    /// in the direction of the y axis.
+    HalfPlane,
+    /// Band model, a strip around the geodesic along the x axis.
+    Band,
+    /// Orthogonal projection of the hyperboloid onto the plane.
+    Gans,
+    /// Azimuthal equidistant projection, keeping distances from the centre.
+    Azimuthal,
+    /// Azimuthal equal-area projection, keeping areas.
+    EqualArea,
 }
 impl Projection {
-    pub fn shader_source(&self) -> wgpu::ShaderModuleDescriptor {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "poincare" => Projection::Poincare,
+            "klein" => Projection::Klein,
+            "hyperboloid" => Projection::Hyperboloid,
+            "half_plane" => Projection::HalfPlane,
+            "band" => Projection::Band,
+            "gans" => Projection::Gans,
+            "azimuthal" => Projection::Azimuthal,
+            "equal_area" => Projection::EqualArea,
+            _ => return None,
+        })
+    }
+
+    /// Point of the projected plane shown at the centre of the screen before
+    /// the view is panned, which is where the origin is projected, and the
+    /// initial scale of the view. The unbounded projections are scaled to
+    /// show a few tiles around the origin.
+    pub fn home(&self) -> (Vector2<f64>, f64) {
+        match self {
+            Projection::HalfPlane => (Vector2::new(0.0, 1.0), 1.0),
+            Projection::Gans => (Vector2::new(0.0, 0.0), 0.1),
+            Projection::Azimuthal => (Vector2::new(0.0, 0.0), 0.25),
+            Projection::EqualArea => (Vector2::new(0.0, 0.0), 0.15),
+            _ => (Vector2::new(0.0, 0.0), 1.0),
+        }
+    }
+
+    /// Height of the pole this is a projection from, as in `pole` in
+    /// `projection.wgsl`, if it is one.
+    pub fn pole(&self) -> Option<f64> {
         match self {
-            Projection::Poincare => wgpu::include_wgsl!("poincare.wgsl"),
-            Projection::Klein => wgpu::include_wgsl!("klein.wgsl"),
-            Projection::Hyperboloid => wgpu::include_wgsl!("hyperboloid.wgsl"),
+            Projection::Klein => Some(0.0),
+            Projection::Poincare => Some(0.5),
+            Projection::Gans => Some(1.0),
+            _ => None,
+        }
+    }
+
+    /// Point of the projected plane `v` is projected to, as in `project` in
+    /// `projection.wgsl`, or `None` in 3D.
+    pub fn project(&self, v: Vector3<f64>) -> Option<Vector2<f64>> {
+        Some(match self {
+            Projection::Hyperboloid => return None,
+            Projection::HalfPlane => {
+                let d = v.z - v.y;
+                Vector2::new(v.x / d, 1.0 / d)
+            }
+            Projection::Band => {
+                Vector2::new(((v.z + v.x) / (v.z - v.x)).ln() / PI, 2.0 * v.y.atan() / PI)
+            }
+            Projection::Azimuthal => {
+                let r = v.truncate().magnitude();
+                let scale = if r > 1e-9 { (v.z + r).ln() / r } else { 1.0 };
+                v.truncate() * scale
+            }
+            Projection::EqualArea => v.truncate() * (2.0 / (1.0 + v.z)).sqrt(),
+            Projection::Poincare | Projection::Klein | Projection::Gans => {
+                project_pole(self.pole()?, v)
+            }
+        })
+    }
+
+    /// Point of the hyperboloid projected to `p`, as in `unproject` in
+    /// `projection.wgsl`, or `None` outside the image or in 3D.
+    pub fn unproject(&self, p: Vector2<f64>) -> Option<Vector3<f64>> {
+        match self {
+            Projection::Hyperboloid => None,
+            Projection::HalfPlane => {
+                if p.y <= 0.0 {
+                    return None;
+                }
+                let x = p.x / p.y;
+                let sum = (1.0 + x * x) * p.y;
+                Some(Vector3::new(
+                    x,
+                    (sum - 1.0 / p.y) / 2.0,
+                    (sum + 1.0 / p.y) / 2.0,
+                ))
+            }
+            Projection::Band => {
+                if p.y.abs() >= 1.0 {
+                    return None;
+                }
+                let y = (p.y * PI / 2.0).tan();
+                let a = p.x * PI / 2.0;
+                let r = (1.0 + y * y).sqrt();
+                Some(Vector3::new(r * a.sinh(), y, r * a.cosh()))
+            }
+            Projection::Azimuthal => {
+                let d = p.magnitude();
+                let scale = if d > 1e-9 { d.sinh() / d } else { 1.0 };
+                Some((p * scale).extend(d.cosh()))
+            }
+            Projection::EqualArea => {
+                let z = 1.0 + p.magnitude2() / 2.0;
+                Some((p * ((1.0 + z) / 2.0).sqrt()).extend(z))
+            }
+            Projection::Poincare | Projection::Klein | Projection::Gans => {
+                unproject_pole(self.pole()?, p)
+            }
+        }
+    }
+}
+
+/// Projection of `v` from the pole at `u`, as in `project_pole` in
+/// `projection.wgsl`.
+pub fn project_pole(u: f64, v: Vector3<f64>) -> Vector2<f64> {
+    v.truncate() * (1.0 - 2.0 * u + 2.0 * u * u) / (v.z * (1.0 - u) + u)
+}
+
+/// Point of the hyperboloid projected to `p` from the pole at `u`, as in
+/// `unproject_pole` in `projection.wgsl`, or `None` outside the image.
+pub fn unproject_pole(u: f64, p: Vector2<f64>) -> Option<Vector3<f64>> {
+    let q = p / (1.0 - 2.0 * u + 2.0 * u * u);
+    let m = q.magnitude();
+    let a = 1.0 - u;
+    let d = 1.0 - m * m * a * a;
+    let s = 1.0 + m * m * (u * u - a * a);
+    if d <= 0.0 || s < 0.0 {
+        return None;
+    }
+    let r = m * (u + a * s.sqrt()) / d;
+    let dir = if m > 0.0 { q / m } else { q };
+    Some((dir * r).extend((1.0 + r * r).sqrt()))
+}
+
+/// How the tiling is turned into pixels.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Renderer {
+    /// Copies of the prototile meshes, up to a given depth.
+    Mesh,
+    /// A triangle covering the screen, with every pixel folded back into the
+    /// domain of the tiling to find its tile.
+    Fold,
+    /// Coloured line segments in the 3D view of the hyperboloid, for the
+    /// overlay of the models.
+    Overlay,
+}
+impl Renderer {
+    /// Source of the shader, which shares the projections with the others.
+    pub fn shader_source(&self) -> wgpu::ShaderModuleDescriptor<'static> {
+        let source = match self {
+            Renderer::Mesh => concat!(include_str!("projection.wgsl"), include_str!("mesh.wgsl")),
+            Renderer::Fold => concat!(include_str!("projection.wgsl"), include_str!("fold.wgsl")),
+            Renderer::Overlay => {
+                concat!(
+                    include_str!("projection.wgsl"),
+                    include_str!("overlay.wgsl")
+                )
+            }
+        };
+        wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
         }
     }
 }
 
 pub struct PipelineLayout {
     pub pipeline: wgpu::PipelineLayout,
+    /// Layouts of the pipelines using [`Renderer::Fold`] and
+    /// [`Renderer::Overlay`].
+    pub fold: wgpu::PipelineLayout,
+    pub overlay: wgpu::PipelineLayout,
     pub camera: CameraBindGroupLayout,
+    pub palette: PaletteBindGroupLayout,
+    pub domain: DomainBindGroupLayout,
+    pub layer: LayerBindGroupLayout,
 }
 impl PipelineLayout {
     pub fn new(device: &Device) -> Self {
         let camera = CameraBindGroupLayout::new(device);
+        let palette = PaletteBindGroupLayout::new(device);
+        let domain = DomainBindGroupLayout::new(device);
+        let layer = LayerBindGroupLayout::new(device);
         PipelineLayout {
             pipeline: device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&camera, &palette, &layer],
+                push_constant_ranges: &[],
+            }),
+            fold: device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&camera, &palette, &domain],
+                push_constant_ranges: &[],
+            }),
+            overlay: device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: None,
                 bind_group_layouts: &[&camera],
                 push_constant_ranges: &[],
             }),
             camera,
+            palette,
+            domain,
+            layer,
         }
     }
 }
@@ -43,38 +235,62 @@ pub struct Pipeline {
     inner: wgpu::RenderPipeline,
 }
 impl Pipeline {
-    pub fn new(
-        device: &Device,
-        projection: Projection,
-        swapchain_format: wgpu::TextureFormat,
-    ) -> Self {
+    pub fn new(device: &Device, renderer: Renderer, swapchain_format: wgpu::TextureFormat) -> Self {
         let layout = Arc::new(PipelineLayout::new(device));
-        Self::with_layout(device, layout, projection, swapchain_format)
+        Self::with_layout(device, layout, renderer, swapchain_format)
     }
 
     pub fn with_layout(
         device: &Device,
         layout: Arc<PipelineLayout>,
-        projection: Projection,
+        renderer: Renderer,
         swapchain_format: wgpu::TextureFormat,
     ) -> Self {
-        let shader = device.create_shader_module(projection.shader_source());
+        let (pipeline_layout, buffers, topology) = match renderer {
+            Renderer::Mesh => (
+                &layout.pipeline,
+                &[
+                    super::Vertex::LAYOUT,
+                    super::Instance::LAYOUT,
+                    super::InstanceFlags::LAYOUT,
+                ][..],
+                wgpu::PrimitiveTopology::TriangleList,
+            ),
+            Renderer::Fold => (&layout.fold, &[][..], wgpu::PrimitiveTopology::TriangleList),
+            Renderer::Overlay => (
+                &layout.overlay,
+                &[LineVertex::LAYOUT][..],
+                wgpu::PrimitiveTopology::LineList,
+            ),
+        };
+        let shader = device.create_shader_module(renderer.shader_source());
         Pipeline {
             inner: device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
                 label: None,
-                layout: Some(&layout.pipeline),
+                layout: Some(pipeline_layout),
                 vertex: wgpu::VertexState {
                     module: &shader,
                     entry_point: "vs_main",
-                    buffers: &[super::Vertex::LAYOUT],
+                    buffers,
                 },
                 fragment: Some(wgpu::FragmentState {
                     module: &shader,
                     entry_point: "fs_main",
                     targets: &[Some(swapchain_format.into())],
                 }),
-                primitive: wgpu::PrimitiveState::default(),
-                depth_stencil: None,
+                primitive: wgpu::PrimitiveState {
+                    topology,
+                    ..wgpu::PrimitiveState::default()
+                },
+                // Whatever is drawn later wins among equal depths, as all of
+                // the flat projections lie at the same depth.
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
                 multisample: wgpu::MultisampleState::default(),
                 multiview: None,
             }),
@@ -89,3 +305,90 @@ impl Deref for Pipeline {
         &self.inner
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROJECTIONS: [Projection; 8] = [
+        Projection::Poincare,
+        Projection::Klein,
+        Projection::Hyperboloid,
+        Projection::HalfPlane,
+        Projection::Band,
+        Projection::Gans,
+        Projection::Azimuthal,
+        Projection::EqualArea,
+    ];
+
+    /// Point of the hyperboloid at distance `d` from the origin, in the
+    /// direction at `angle` from the x axis.
+    fn at(d: f64, angle: f64) -> Vector3<f64> {
+        let (sin, cos) = angle.sin_cos();
+        Vector3::new(cos * d.sinh(), sin * d.sinh(), d.cosh())
+    }
+
+    fn points() -> Vec<Vector3<f64>> {
+        let mut points = vec![at(0.0, 0.0)];
+        for d in [0.1, 1.0, 2.5] {
+            points.extend([0.3, 1.9, 3.5, 5.2].map(|angle| at(d, angle)));
+        }
+        points
+    }
+
+    #[test]
+    fn unprojecting_undoes_projecting() {
+        for projection in PROJECTIONS {
+            for v in points() {
+                let Some(p) = projection.project(v) else {
+                    assert_eq!(projection, Projection::Hyperboloid);
+                    continue;
+                };
+                let w = projection.unproject(p).unwrap();
+                assert!((w - v).magnitude() < 1e-9 * v.z, "{:?} {:?}", projection, v);
+            }
+        }
+    }
+
+    #[test]
+    fn unprojecting_from_blended_poles_undoes_projecting() {
+        for u in [0.0, 0.2, 0.5, 0.8, 1.0] {
+            for v in points() {
+                let w = unproject_pole(u, project_pole(u, v)).unwrap();
+                assert!((w - v).magnitude() < 1e-9 * v.z, "{} {:?}", u, v);
+            }
+        }
+    }
+
+    #[test]
+    fn radii_of_azimuthal_projections() {
+        for d in [0.0, 0.1, 1.0, 2.5] {
+            let radius =
+                |projection: Projection| projection.project(at(d, 1.2)).unwrap().magnitude();
+            assert!((radius(Projection::Poincare) - (d / 2.0).tanh()).abs() < 1e-12);
+            assert!((radius(Projection::Klein) - d.tanh()).abs() < 1e-12);
+            assert!((radius(Projection::Gans) - d.sinh()).abs() < 1e-12);
+            assert!((radius(Projection::Azimuthal) - d).abs() < 1e-12);
+            assert!((radius(Projection::EqualArea) - 2.0 * (d / 2.0).sinh()).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn distances_along_axes() {
+        for d in [0.1, 1.0, 2.5] {
+            let band = Projection::Band.project(at(d, 0.0)).unwrap();
+            assert!((band - Vector2::new(2.0 * d / PI, 0.0)).magnitude() < 1e-12);
+            let half_plane = Projection::HalfPlane.project(at(d, PI / 2.0)).unwrap();
+            assert!((half_plane - Vector2::new(0.0, d.exp())).magnitude() < 1e-12 * d.exp());
+        }
+    }
+
+    #[test]
+    fn origin_is_projected_home() {
+        for projection in PROJECTIONS {
+            if let Some(p) = projection.project(at(0.0, 0.0)) {
+                assert_eq!(p, projection.home().0, "{:?}", projection);
+            }
+        }
+    }
+}
@@ -0,0 +1,19 @@
+struct VertexOutput {
+    @location(0)
+    color: vec3<f32>,
+    @builtin(position)
+    pos: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@location(0) pos: vec3<f32>, @location(1) color: vec3<f32>) -> VertexOutput {
+    var output: VertexOutput;
+    output.pos = camera.perspective * vec4<f32>(pos, 1.0);
+    output.color = color;
+    return output;
+}
+
+@fragment
+fn fs_main(@location(0) color: vec3<f32>) -> @location(0) vec4<f32> {
+    return vec4<f32>(color, 1.0);
+}
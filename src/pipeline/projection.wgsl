@@ -0,0 +1,229 @@
+// Projections shared by the shaders, which are appended to this file.
+
+struct Camera {
+    viewport: mat4x4<f32>,
+    transform: mat4x4<f32>,
+    // Perspective view of the hyperboloid in 3D, and its inverse.
+    perspective: mat4x4<f32>,
+    inverse_perspective: mat4x4<f32>,
+    // Projections being blended, numbered in the order of `Projection`, and
+    // how far the blend has gone from `previous` to `current`.
+    previous: u32,
+    current: u32,
+    blend: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+let PI: f32 = 3.14159265358979;
+
+// Number of `Projection::Hyperboloid`, which is shown in 3D.
+let HYPERBOLOID: u32 = 2u;
+
+// Depth of the projections onto the screen plane.
+let FLAT_DEPTH: f32 = 0.5;
+
+fn minkowski(u: vec3<f32>, v: vec3<f32>) -> f32 {
+    return u.x * v.x + u.y * v.y - u.z * v.z;
+}
+
+// Height of the pole of the projections from a point below the hyperboloid,
+// as the fraction u of the way from the origin, giving the Klein model, to
+// infinitely far down, giving the Gans model, or -1 for other projections.
+fn pole(projection: u32) -> f32 {
+    switch projection {
+        // Klein.
+        case 1u: {
+            return 0.0;
+        }
+        // Poincaré.
+        case 0u: {
+            return 0.5;
+        }
+        // Gans.
+        case 5u: {
+            return 1.0;
+        }
+        default: {
+            return -1.0;
+        }
+    }
+}
+
+// Projects `v` from the pole at `u`, scaled so that the Klein, Poincaré and
+// Gans models come out at u = 0, 1/2 and 1.
+fn project_pole(u: f32, v: vec3<f32>) -> vec2<f32> {
+    return v.xy * (1.0 - 2.0 * u + 2.0 * u * u) / (v.z * (1.0 - u) + u);
+}
+
+// Inverse of `project_pole`, or zero outside the image.
+fn unproject_pole(u: f32, p: vec2<f32>) -> vec3<f32> {
+    let q = p / (1.0 - 2.0 * u + 2.0 * u * u);
+    let m = length(q);
+    let a = 1.0 - u;
+    // The distance r from the axis solves r = m (a sqrt(1 + r^2) + u).
+    let d = 1.0 - m * m * a * a;
+    let s = 1.0 + m * m * (u * u - a * a);
+    if d <= 0.0 || s < 0.0 {
+        return vec3<f32>(0.0);
+    }
+    let r = m * (u + a * sqrt(s)) / d;
+    var dir = vec2<f32>(0.0);
+    if m > 0.0 {
+        dir = q / m;
+    }
+    return vec3<f32>(dir * r, sqrt(1.0 + r * r));
+}
+
+fn project(projection: u32, v: vec3<f32>) -> vec2<f32> {
+    switch projection {
+        // Half-plane.
+        case 3u: {
+            // Seen from the ideal point in the direction of the y axis.
+            let d = v.z - v.y;
+            return vec2<f32>(v.x / d, 1.0 / d);
+        }
+        // Band.
+        case 4u: {
+            // Twice the hyperbolic arctangent of x / z, and the arctangent of
+            // y, scaled so that the strip spans the height of the screen.
+            return vec2<f32>(log((v.z + v.x) / (v.z - v.x)) / PI, 2.0 * atan(v.y) / PI);
+        }
+        // Azimuthal equidistant.
+        case 6u: {
+            // The distance from the origin is acosh(z), and sinh of it is the
+            // length of v.xy.
+            let r = length(v.xy);
+            var scale = 1.0;
+            if r > 1e-6 {
+                scale = log(v.z + r) / r;
+            }
+            return v.xy * scale;
+        }
+        // Equal-area.
+        case 7u: {
+            // The disk within distance d of the origin has area 2π(cosh d - 1).
+            return v.xy * sqrt(2.0 / (1.0 + v.z));
+        }
+        default: {
+            return project_pole(pole(projection), v);
+        }
+    }
+}
+
+// Inverse of `project`, or zero outside the image.
+fn unproject(projection: u32, p: vec2<f32>) -> vec3<f32> {
+    switch projection {
+        // Half-plane.
+        case 3u: {
+            if p.y <= 0.0 {
+                return vec3<f32>(0.0);
+            }
+            // The point's z - y is 1 / p.y and its z + y is (1 + x^2) p.y.
+            let x = p.x / p.y;
+            let sum = (1.0 + x * x) * p.y;
+            return vec3<f32>(x, (sum - 1.0 / p.y) / 2.0, (sum + 1.0 / p.y) / 2.0);
+        }
+        // Band.
+        case 4u: {
+            if abs(p.y) >= 1.0 {
+                return vec3<f32>(0.0);
+            }
+            let y = tan(p.y * PI / 2.0);
+            let a = p.x * PI / 2.0;
+            let r = sqrt(1.0 + y * y);
+            return vec3<f32>(r * sinh(a), y, r * cosh(a));
+        }
+        // Azimuthal equidistant.
+        case 6u: {
+            let d = length(p);
+            var scale = 1.0;
+            if d > 1e-6 {
+                scale = sinh(d) / d;
+            }
+            return vec3<f32>(p * scale, cosh(d));
+        }
+        // Equal-area.
+        case 7u: {
+            let z = 1.0 + dot(p, p) / 2.0;
+            return vec3<f32>(p * sqrt((1.0 + z) / 2.0), z);
+        }
+        default: {
+            return unproject_pole(pole(projection), p);
+        }
+    }
+}
+
+// Clip coordinates of `v` in `projection`.
+fn clip(projection: u32, v: vec3<f32>) -> vec4<f32> {
+    if projection == HYPERBOLOID {
+        return camera.perspective * vec4<f32>(v, 1.0);
+    }
+    return camera.viewport * vec4<f32>(project(projection, v), 0.0, 1.0);
+}
+
+// Clip coordinates of `v`, as seen by the camera, blending the camera's
+// projections. Projections from a pole are blended by moving the pole.
+fn projected(v: vec3<f32>) -> vec4<f32> {
+    let a = pole(camera.previous);
+    let b = pole(camera.current);
+    if a >= 0.0 && b >= 0.0 {
+        let p = project_pole(mix(a, b, camera.blend), v);
+        return camera.viewport * vec4<f32>(p, 0.0, 1.0);
+    }
+    return mix(clip(camera.previous, v), clip(camera.current, v), camera.blend);
+}
+
+// Undoes the viewport, giving the point in the plane of the projection.
+fn unview(screen: vec2<f32>) -> vec2<f32> {
+    let scale = vec2<f32>(camera.viewport[0].x, camera.viewport[1].y);
+    return (screen - camera.viewport[3].xy) / scale;
+}
+
+// Point of the hyperboloid seen at `screen` in the 3D view, with its depth,
+// or zero if the view ray misses it.
+fn raycast(screen: vec2<f32>) -> vec4<f32> {
+    let near = camera.inverse_perspective * vec4<f32>(screen, 0.0, 1.0);
+    let far = camera.inverse_perspective * vec4<f32>(screen, 1.0, 1.0);
+    let o = near.xyz / near.w;
+    let d = far.xyz / far.w - o;
+    // Solve <o + t d, o + t d> = -1 for the nearest t with a positive z.
+    let a = minkowski(d, d);
+    let b = minkowski(o, d);
+    let c = minkowski(o, o) + 1.0;
+    let disc = b * b - a * c;
+    if disc < 0.0 {
+        return vec4<f32>(0.0);
+    }
+    let roots = vec2<f32>(-b - sqrt(disc), -b + sqrt(disc)) / a;
+    let t0 = min(roots.x, roots.y);
+    let t1 = max(roots.x, roots.y);
+    var t = t0;
+    if t0 < 0.0 || o.z + t0 * d.z <= 0.0 {
+        t = t1;
+    }
+    let v = o + t * d;
+    if t < 0.0 || t > 1.0 || v.z <= 0.0 {
+        return vec4<f32>(0.0);
+    }
+    let hit = camera.perspective * vec4<f32>(v, 1.0);
+    return vec4<f32>(v, hit.z / hit.w);
+}
+
+// Inverse of `projected` at `screen`, with the depth there, or zero outside
+// the image. Blends other than by moving the pole have no inverse, so the
+// nearer projection is used.
+fn unprojected(screen: vec2<f32>) -> vec4<f32> {
+    let a = pole(camera.previous);
+    let b = pole(camera.current);
+    if a >= 0.0 && b >= 0.0 {
+        let v = unproject_pole(mix(a, b, camera.blend), unview(screen));
+        return vec4<f32>(v, FLAT_DEPTH);
+    }
+    let projection = select(camera.current, camera.previous, camera.blend < 0.5);
+    if projection == HYPERBOLOID {
+        return raycast(screen);
+    }
+    return vec4<f32>(unproject(projection, unview(screen)), FLAT_DEPTH);
+}
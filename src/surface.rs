@@ -1,20 +1,60 @@
 use std::ops::Deref;
 
 use cgmath::Vector2;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, MutexGuard};
 use wgpu::{Device, Queue};
 
 use crate::window::Window;
 
+/// Format of the depth buffer shared by the pipelines.
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Depth buffer of `size` pixels.
+pub fn create_depth(device: &Device, size: Vector2<u32>) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("depth texture"),
+            size: wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
 pub struct State {
     pub device: Device,
     pub queue: Queue,
 }
+impl State {
+    /// Opens the device of `adapter`, asking for no more than WebGL offers.
+    pub async fn new(adapter: &wgpu::Adapter) -> Result<Self, wgpu::RequestDeviceError> {
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: None,
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::downlevel_webgl2_defaults()
+                        .using_resolution(adapter.limits()),
+                },
+                None,
+            )
+            .await?;
+        Ok(State { device, queue })
+    }
+}
 
 pub struct Surface<W> {
     pub window: W,
     surface: wgpu::Surface,
     config: Mutex<wgpu::SurfaceConfiguration>,
+    depth: Mutex<wgpu::TextureView>,
     pub swapchain_format: wgpu::TextureFormat,
 }
 impl<W: Window> Surface<W> {
@@ -30,18 +70,7 @@ impl<W: Window> Surface<W> {
             .await
             .expect("failed to find an appropriate adapter");
 
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    features: wgpu::Features::empty(),
-                    limits: wgpu::Limits::downlevel_webgl2_defaults()
-                        .using_resolution(adapter.limits()),
-                },
-                None,
-            )
-            .await
-            .expect("failed to create device");
+        let state = State::new(&adapter).await.expect("failed to create device");
 
         let swapchain_format = surface.get_supported_formats(&adapter)[0];
         let size = window.size();
@@ -53,14 +82,16 @@ impl<W: Window> Surface<W> {
             present_mode: wgpu::PresentMode::Fifo,
             alpha_mode: surface.get_supported_alpha_modes(&adapter)[0],
         };
-        surface.configure(&device, &config);
+        surface.configure(&state.device, &config);
+        let depth = create_depth(&state.device, size);
 
         (
-            State { device, queue },
+            state,
             Surface {
                 window,
                 surface,
                 config: Mutex::new(config),
+                depth: Mutex::new(depth),
                 swapchain_format,
             },
         )
@@ -71,6 +102,12 @@ impl<W: Window> Surface<W> {
         config.width = size.x;
         config.height = size.y;
         self.surface.configure(&state.device, &config);
+        *self.depth.lock() = create_depth(&state.device, size);
+    }
+
+    /// View of the depth buffer, matching the size of the surface.
+    pub fn depth(&self) -> MutexGuard<'_, wgpu::TextureView> {
+        self.depth.lock()
     }
 
     pub fn aspect_ratio(&self) -> f64 {
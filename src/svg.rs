@@ -0,0 +1,443 @@
+//! Exporting tilings as SVG images for print, with the sides of the tiles
+//! drawn as exact curves where the projection allows.
+use std::fmt::{self, Write};
+
+use cgmath::{InnerSpace, Matrix3, One, Vector2, Vector3};
+
+use crate::pipeline::Projection;
+use crate::tiling::{minkowski, Tiling, IDEAL_CUTOFF};
+use crate::Color;
+
+/// Relative size below which the normal of a side is taken to lie in the plane,
+/// and the length of a side to vanish.
+const EPSILON: f64 = 1e-9;
+
+/// Segments a side is split into in projections drawing it as a polyline, and
+/// a side running to an ideal point up to the cutoff.
+const SIDE_SEGMENTS: usize = 16;
+const RAY_SEGMENTS: usize = 32;
+
+/// Width of the outline drawn around each tile in its own colour, in pixels,
+/// covering the seams antialiasing leaves between neighbouring tiles.
+const SEAM_WIDTH: f64 = 0.5;
+
+/// Why a tiling cannot be exported.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SvgError {
+    /// The projection has no flat image, as the 3D view of the hyperboloid.
+    Projection(Projection),
+}
+impl fmt::Display for SvgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SvgError::Projection(projection) => {
+                write!(f, "the {:?} projection cannot be exported", projection)
+            }
+        }
+    }
+}
+impl std::error::Error for SvgError {}
+
+/// Part of the projected plane shown in the image.
+#[derive(Debug, Clone, Copy)]
+pub struct SvgView {
+    pub projection: Projection,
+    /// Isometry taking the plane to the view, as the camera's.
+    pub isometry: Matrix3<f64>,
+    /// Point of the projected plane at the centre of the image, and the scale
+    /// of the view, where the height of the image is 2 / `scale`.
+    pub centre: Vector2<f64>,
+    pub scale: f64,
+    /// Size of the image in pixels.
+    pub size: Vector2<f64>,
+    /// Tiles smaller than this many pixels across are left out.
+    pub min_size: f64,
+}
+impl SvgView {
+    /// View of `projection` as the camera first shows it, in an image of
+    /// `size` pixels.
+    pub fn new(projection: Projection, size: Vector2<f64>) -> Self {
+        let (centre, scale) = projection.home();
+        SvgView {
+            projection,
+            isometry: Matrix3::one(),
+            centre,
+            scale,
+            size,
+            min_size: 0.0,
+        }
+    }
+
+    /// Pixel of the image showing the point `p` of the projected plane.
+    fn pixel(&self, p: Vector2<f64>) -> Vector2<f64> {
+        let k = self.scale * self.size.y / 2.0;
+        let offset = (p - self.centre) * k;
+        Vector2::new(self.size.x / 2.0 + offset.x, self.size.y / 2.0 - offset.y)
+    }
+
+    /// Outline of the tile with `corners`, as seen by the camera, or `None`
+    /// if it is too small or lies outside the image.
+    fn outline(&self, corners: &[Vector3<f64>]) -> Option<Outline> {
+        let outline = match self.projection {
+            Projection::Poincare => self.poincare_outline(corners),
+            Projection::Klein => {
+                let point = |v: Vector3<f64>| self.pixel(v.truncate() / v.z);
+                Outline {
+                    start: point(corners[0]),
+                    segments: around(corners)
+                        .map(|(_, b)| Segment::Line(point(b)))
+                        .collect(),
+                }
+            }
+            _ => self.sampled_outline(corners)?,
+        };
+        let (min, max) = outline.bounds();
+        let extent = max - min;
+        let visible = max.x >= 0.0 && max.y >= 0.0 && min.x <= self.size.x && min.y <= self.size.y;
+        (visible && extent.x.max(extent.y) >= self.min_size).then_some(outline)
+    }
+
+    /// Outline in the Poincaré disk, where geodesics are arcs of circles
+    /// meeting the rim at right angles, or diameters.
+    fn poincare_outline(&self, corners: &[Vector3<f64>]) -> Outline {
+        let project = |v: Vector3<f64>| {
+            // Ideal points lie on the rim, as in the Klein model.
+            let z = if is_ideal(v) { v.z } else { v.z + 1.0 };
+            v.truncate() / z
+        };
+        let k = self.scale * self.size.y / 2.0;
+        let segments = around(corners)
+            .map(|(a, b)| {
+                let to = self.pixel(project(b));
+                // The plane through the origin containing the geodesic has
+                // Euclidean normal m, and meets the disk in the circle of
+                // points p with |p|² + 2 m.xy · p / m.z + 1 = 0.
+                let m = a.cross(b);
+                if m.z.abs() <= EPSILON * m.magnitude() {
+                    return Segment::Line(to);
+                }
+                let centre = -m.truncate() / m.z;
+                let (from, end) = (project(a) - centre, project(b) - centre);
+                Segment::Arc {
+                    radius: (centre.magnitude2() - 1.0).max(0.0).sqrt() * k,
+                    // The image is the plane seen the same way up, so turning
+                    // clockwise in one is turning clockwise in the other.
+                    sweep: from.x * end.y - from.y * end.x < 0.0,
+                    to,
+                }
+            })
+            .collect();
+        Outline {
+            start: self.pixel(project(corners[0])),
+            segments,
+        }
+    }
+
+    /// Outline as a polyline through points along the sides, or `None` if no
+    /// point of it can be projected.
+    fn sampled_outline(&self, corners: &[Vector3<f64>]) -> Option<Outline> {
+        let mut points = Vec::new();
+        for (a, b) in around(corners) {
+            let samples = side_samples(a, b);
+            // A finite corner ends the previous side.
+            let skip = usize::from(!points.is_empty() && !is_ideal(a));
+            points.extend(
+                samples[skip..]
+                    .iter()
+                    .filter_map(|&v| self.projection.project(v))
+                    .map(|p| self.pixel(p)),
+            );
+        }
+        let (&start, rest) = points.split_first()?;
+        Some(Outline {
+            start,
+            segments: rest.iter().map(|&p| Segment::Line(p)).collect(),
+        })
+    }
+}
+
+/// Pairs of consecutive corners of a tile, joined by its sides.
+fn around(corners: &[Vector3<f64>]) -> impl Iterator<Item = (Vector3<f64>, Vector3<f64>)> + '_ {
+    (0..corners.len()).map(|i| (corners[i], corners[(i + 1) % corners.len()]))
+}
+
+/// Whether the corner `v` is an ideal point. Finite points lie on the
+/// hyperboloid and ideal ones on the light cone, which rounding keeps apart
+/// even far from the origin.
+fn is_ideal(v: Vector3<f64>) -> bool {
+    minkowski(v, v) > -0.5
+}
+
+/// Points along the geodesic from `a` to `b`, both included, with the ends
+/// at ideal points cut off.
+fn side_samples(a: Vector3<f64>, b: Vector3<f64>) -> Vec<Vector3<f64>> {
+    match (is_ideal(a), is_ideal(b)) {
+        (false, false) => {
+            let d = (-minkowski(a, b)).max(1.0).acosh();
+            if d < EPSILON {
+                return vec![a, b];
+            }
+            (0..=SIDE_SEGMENTS)
+                .map(|k| {
+                    let t = k as f64 / SIDE_SEGMENTS as f64;
+                    (a * ((1.0 - t) * d).sinh() + b * (t * d).sinh()) / d.sinh()
+                })
+                .collect()
+        }
+        (false, true) => ray(a, b),
+        (true, false) => {
+            let mut samples = ray(b, a);
+            samples.reverse();
+            samples
+        }
+        (true, true) => {
+            // Go both ways from the point of the geodesic between the ends.
+            let middle = (a + b) / (-2.0 * minkowski(a, b)).sqrt();
+            let mut samples = ray(middle, a);
+            samples.reverse();
+            samples.extend(ray(middle, b).into_iter().skip(1));
+            samples
+        }
+    }
+}
+
+/// Points along the geodesic from the finite point `a` towards the ideal
+/// point `l`, up to the cutoff the meshes use.
+fn ray(a: Vector3<f64>, l: Vector3<f64>) -> Vec<Vector3<f64>> {
+    // Unit tangent at a pointing towards l.
+    let t = l / -minkowski(l, a) - a;
+    (0..=RAY_SEGMENTS)
+        .map(|k| {
+            let s = IDEAL_CUTOFF * k as f64 / RAY_SEGMENTS as f64;
+            a * s.cosh() + t * s.sinh()
+        })
+        .collect()
+}
+
+/// Piece of an outline, given by where it ends.
+enum Segment {
+    Line(Vector2<f64>),
+    /// Arc of a circle shorter than a semicircle, turning clockwise as seen
+    /// if `sweep`.
+    Arc {
+        radius: f64,
+        sweep: bool,
+        to: Vector2<f64>,
+    },
+}
+impl Segment {
+    fn end(&self) -> Vector2<f64> {
+        match *self {
+            Segment::Line(to) | Segment::Arc { to, .. } => to,
+        }
+    }
+}
+
+/// Closed outline of a tile, in pixels.
+struct Outline {
+    start: Vector2<f64>,
+    segments: Vec<Segment>,
+}
+impl Outline {
+    /// Corners of the box around the ends of the segments, which is near
+    /// enough to the box around the outline for culling.
+    fn bounds(&self) -> (Vector2<f64>, Vector2<f64>) {
+        self.segments
+            .iter()
+            .map(Segment::end)
+            .fold((self.start, self.start), |(min, max), p| {
+                (
+                    Vector2::new(min.x.min(p.x), min.y.min(p.y)),
+                    Vector2::new(max.x.max(p.x), max.y.max(p.y)),
+                )
+            })
+    }
+}
+impl fmt::Display for Outline {
+    /// Writes the path data of the outline.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "M{:.2},{:.2}", self.start.x, self.start.y)?;
+        for segment in &self.segments {
+            match *segment {
+                Segment::Line(to) => write!(f, "L{:.2},{:.2}", to.x, to.y)?,
+                Segment::Arc { radius, sweep, to } => write!(
+                    f,
+                    "A{:.2},{:.2} 0 0 {} {:.2},{:.2}",
+                    radius, radius, sweep as u8, to.x, to.y
+                )?,
+            }
+        }
+        f.write_str("Z")
+    }
+}
+
+/// Draws the tiles of `tiling` up to `depth`, coloured from `colors` as the
+/// mesh is, as an SVG image of `view`.
+pub fn export(
+    tiling: &dyn Tiling,
+    colors: &[Color],
+    depth: usize,
+    view: &SvgView,
+) -> Result<String, SvgError> {
+    if view.projection == Projection::Hyperboloid {
+        return Err(SvgError::Projection(view.projection));
+    }
+    // The pieces of the tiles are drawn as the mesh places and colours them.
+    let mesh = tiling.generate(colors, depth);
+    let palette = &mesh.palette;
+    // Paths grouped by colour, as most tiles share theirs with many others.
+    let mut paths = vec![String::new(); palette.len()];
+    for (part, corners) in mesh.parts.iter().zip(tiling.prototile_corners()) {
+        for i in part.instances.clone() {
+            let tr = view.isometry * mesh.placed[i as usize].transform;
+            let corners: Vec<_> = corners.iter().map(|&v| tr * v).collect();
+            if let Some(outline) = view.outline(&corners) {
+                let color = mesh.instances[i as usize].color as usize;
+                writeln!(paths[color], "<path d=\"{}\"/>", outline).unwrap();
+            }
+        }
+    }
+
+    let Vector2 {
+        x: width,
+        y: height,
+    } = view.size;
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+         viewBox=\"0 0 {} {}\">",
+        width, height, width, height
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        "<rect width=\"{}\" height=\"{}\" fill=\"#ffffff\"/>",
+        width, height
+    )
+    .unwrap();
+    for (color, paths) in palette.iter().zip(&paths) {
+        if paths.is_empty() {
+            continue;
+        }
+        let hex = format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b);
+        writeln!(
+            svg,
+            "<g fill=\"{}\" stroke=\"{}\" stroke-width=\"{}\" stroke-linejoin=\"round\">",
+            hex, hex, SEAM_WIDTH
+        )
+        .unwrap();
+        svg.push_str(paths);
+        svg.push_str("</g>\n");
+    }
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiling::TilingGenerator;
+
+    fn view() -> SvgView {
+        SvgView::new(Projection::Poincare, Vector2::new(300.0, 200.0))
+    }
+
+    /// Point of the hyperboloid at `p` in the Poincaré disk.
+    fn lift(p: Vector2<f64>) -> Vector3<f64> {
+        (p * 2.0).extend(1.0 + p.magnitude2()) / (1.0 - p.magnitude2())
+    }
+
+    /// Centre of the circle meeting the rim at right angles through `p` and
+    /// `q`, which satisfies 2 c · p = |p|² + 1 for both.
+    fn orthogonal_centre(p: Vector2<f64>, q: Vector2<f64>) -> Vector2<f64> {
+        let det = p.perp_dot(q);
+        let (a, b) = ((p.magnitude2() + 1.0) / 2.0, (q.magnitude2() + 1.0) / 2.0);
+        Vector2::new(a * q.y - b * p.y, b * p.x - a * q.x) / det
+    }
+
+    /// The arc of the only side of an outline from `a` to `b`.
+    fn arc(view: &SvgView, a: Vector3<f64>, b: Vector3<f64>) -> (Vector2<f64>, f64, bool) {
+        let outline = view.poincare_outline(&[a, b]);
+        match outline.segments[0] {
+            Segment::Arc { radius, sweep, to } => (to, radius, sweep),
+            Segment::Line(_) => panic!("{:?} to {:?} is not an arc", a, b),
+        }
+    }
+
+    #[test]
+    fn arcs_are_orthogonal_circles() {
+        let view = view();
+        let k = view.scale * view.size.y / 2.0;
+        for (p, q) in [
+            (Vector2::new(0.3, 0.1), Vector2::new(-0.2, 0.5)),
+            (Vector2::new(-0.7, -0.1), Vector2::new(0.1, -0.8)),
+            (Vector2::new(0.9, 0.05), Vector2::new(0.85, 0.3)),
+        ] {
+            let (a, b) = (lift(p), lift(q));
+            let (to, radius, _) = arc(&view, a, b);
+            assert!((to - view.pixel(q)).magnitude() < 1e-9);
+            let centre = orthogonal_centre(p, q);
+            let r = (centre.magnitude2() - 1.0).sqrt();
+            assert!(
+                (radius - r * k).abs() < 1e-6 * k,
+                "{} against {}",
+                radius,
+                r * k
+            );
+            // The midpoint of the geodesic is on the circle as well.
+            let middle = a + b;
+            let middle = middle.truncate() / (middle.z + (-minkowski(middle, middle)).sqrt());
+            assert!(((middle - centre).magnitude() - r).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn sweep_turns_through_the_geodesic() {
+        let view = view();
+        for (p, q) in [
+            (Vector2::new(0.3, 0.1), Vector2::new(-0.2, 0.5)),
+            (Vector2::new(-0.2, 0.5), Vector2::new(0.3, 0.1)),
+            (Vector2::new(0.1, -0.8), Vector2::new(-0.7, -0.1)),
+            (Vector2::new(-0.7, -0.1), Vector2::new(0.1, -0.8)),
+        ] {
+            let (_, _, sweep) = arc(&view, lift(p), lift(q));
+            // The flag is set when the short arc turns the positive way in
+            // the image, whose y axis points down.
+            let centre = view.pixel(orthogonal_centre(p, q));
+            let (from, to) = (view.pixel(p) - centre, view.pixel(q) - centre);
+            assert_eq!(sweep, from.perp_dot(to) > 0.0, "{:?} to {:?}", p, q);
+        }
+    }
+
+    #[test]
+    fn sides_reach_ideal_vertices() {
+        let view = view();
+        let k = view.scale * view.size.y / 2.0;
+        let p = Vector2::new(0.2, -0.3);
+        for angle in [0.4_f64, 2.0, -2.5] {
+            let rim = Vector2::new(angle.cos(), angle.sin());
+            let (to, radius, _) = arc(&view, lift(p), rim.extend(1.0));
+            assert!((to - view.pixel(rim)).magnitude() < 1e-9);
+            let r = (orthogonal_centre(p, rim).magnitude2() - 1.0).sqrt();
+            assert!((radius - r * k).abs() < 1e-6 * k);
+        }
+        // Sides through the centre of the disk are diameters.
+        let outline = view.poincare_outline(&[Vector3::unit_z(), Vector3::new(0.6, 0.8, 1.0)]);
+        assert!(matches!(outline.segments[0], Segment::Line(_)));
+    }
+
+    #[test]
+    fn exports_tilings_in_pieces() {
+        let view = view();
+        for tiling in [
+            TilingGenerator::try_from_wythoff("2 3 | 7").unwrap(),
+            TilingGenerator::try_from_schlafli(None, Some(4)).unwrap(),
+        ] {
+            let svg = export(&tiling, crate::COLORS, 2, &view).unwrap();
+            let mesh = tiling.generate(crate::COLORS, 2);
+            // The disk fits in the image, so every piece is drawn.
+            assert_eq!(svg.matches("<path").count(), mesh.instances.len());
+        }
+    }
+}
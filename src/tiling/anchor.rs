@@ -0,0 +1,389 @@
+//! Colours of the tiles kept as the camera is moved back over the tile
+//! around the origin.
+//!
+//! The walk colours the tiles by how it reached them from the first one, so
+//! once the camera has been moved back, the tiles of the mesh must take the
+//! colours of the tiles they stand for, around the anchor. Those are found by
+//! going out along the way the walk took to the anchor a layer at a time,
+//! keeping only the tiles near it: the ways the walk takes to neighbouring
+//! tiles stay close to one another, so the tiles around the anchor are only
+//! reached through those. Along the way, the tiles are placed against the
+//! tile of their layer on the way to the anchor, and their transforms are
+//! snapped to exact ones, so that rounding errors don't build up.
+use std::cmp::Ordering;
+use std::f64::consts::TAU;
+
+use cgmath::{Matrix3, One, Rad, Vector3};
+
+use super::coverage::{cosh_distance, Centres};
+use super::ideal::{child_corners, fill_corners};
+use super::{forward, rotation, Paint, TilingGenerator};
+use crate::{lorentz_inverse, InstancedMesh};
+
+/// Distance, in widths of a tile, by which the ways the walk takes to the
+/// tiles around the anchor may stray from the way to the anchor.
+const STRAY: f64 = 2.0;
+
+/// Tile of the fragment tree the tile around the origin of the mesh stands
+/// for, once the camera has been moved back over another tile.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Anchor {
+    /// Sides crossed on the way to the tile from the first one.
+    path: Vec<u16>,
+    /// Number of sides the tile around the origin is turned by within the
+    /// tile it stands for: its side `i` is side `i + turn` of that tile.
+    turn: usize,
+}
+impl Anchor {
+    fn is_origin(&self) -> bool {
+        self.path.is_empty() && self.turn == 0
+    }
+}
+
+/// Tile of the fragment tree found on the way to the anchor.
+#[derive(Debug, Clone)]
+struct Node {
+    /// Index of the tile it was reached from, and the side crossed.
+    parent: Option<(usize, u16)>,
+    id: u16,
+    /// Slots of the corners, for tilings painted by corner.
+    corners: Vec<usize>,
+    /// Transform of the tile relative to the tile it is placed against.
+    transform: Matrix3<f64>,
+}
+
+/// Tile of the fragment tree standing for a tile of the mesh.
+struct Found {
+    id: u16,
+    /// Slots of the corners, numbered as in the tile of the mesh.
+    corners: Vec<usize>,
+    /// Number of sides the tile of the mesh is turned by within it.
+    turn: usize,
+    node: usize,
+}
+
+/// Transforms of the tiles within some distance of the origin, exact up to
+/// a few roundings, for snapping transforms to.
+struct Ball {
+    transforms: Vec<Matrix3<f64>>,
+    centres: Vec<Vector3<f64>>,
+}
+
+impl TilingGenerator {
+    /// Transforms taking a tile to its neighbours, by side.
+    fn steps(&self) -> Vec<Matrix3<f64>> {
+        let rt = rotation(self.sides);
+        (0..self.sides)
+            .scan(forward(self.len), |tr, _| {
+                let tr1 = *tr;
+                *tr = rt * tr1;
+                Some(tr1)
+            })
+            .collect()
+    }
+
+    /// Cosh of half the distance between neighbouring tile centres, under
+    /// which two centres are the same.
+    fn threshold(&self) -> f64 {
+        (0.5 * self.len.asinh()).cosh()
+    }
+
+    /// Finds the tiles whose centres lie within `radius` of the origin by
+    /// going from tile to tile across every side.
+    fn ball(&self, radius: f64) -> Ball {
+        let steps = self.steps();
+        let threshold = self.threshold();
+        let mut ball = Ball {
+            transforms: vec![Matrix3::one()],
+            centres: vec![Vector3::unit_z()],
+        };
+        let mut frontier = 0..1;
+        while !frontier.is_empty() {
+            let start = ball.transforms.len();
+            for i in frontier {
+                for step in &steps {
+                    let tr = ball.transforms[i] * step;
+                    let c = tr * Vector3::unit_z();
+                    if c.z <= radius.cosh()
+                        && ball
+                            .centres
+                            .iter()
+                            .all(|&v| cosh_distance(v, c) >= threshold)
+                    {
+                        ball.transforms.push(tr);
+                        ball.centres.push(c);
+                    }
+                }
+            }
+            frontier = start..ball.transforms.len();
+        }
+        ball
+    }
+
+    /// Returns the number of sides the tile placed by `b` is turned by
+    /// within the same tile placed by `a`.
+    fn turn_between(&self, a: Matrix3<f64>, b: Matrix3<f64>) -> usize {
+        let x = (lorentz_inverse(a) * b).x;
+        let turns = x.y.atan2(x.x) / TAU * self.sides as f64;
+        (turns.round() as isize).rem_euclid(self.sides as isize) as usize
+    }
+
+    /// Returns `tr` turned by `turn` sides within the tile it places.
+    fn turned(&self, tr: Matrix3<f64>, turn: usize) -> Matrix3<f64> {
+        tr * Matrix3::from_angle_z(Rad(TAU * turn as f64 / self.sides as f64))
+    }
+
+    /// Walks the fragment tree along `path` and beyond, returning the tiles
+    /// found, along with those within `reach` of the anchor at the end of the
+    /// path, which are placed against it. The tiles of the layers further
+    /// from it are placed against the tile of their layer on the path
+    /// instead, and kept only if they are near it.
+    fn around(&self, path: &[u16], reach: f64, slots: usize) -> (Vec<Node>, Vec<usize>) {
+        let steps = self.steps();
+        let stray = STRAY * self.len.asinh();
+        let ball = self.ball(stray + self.len.asinh());
+        let lookup = Centres::new(&ball.centres, self.threshold());
+        let snap = |tr: Matrix3<f64>| match lookup.find(tr * Vector3::unit_z()).next() {
+            Some(i) => self.turned(
+                ball.transforms[i],
+                self.turn_between(ball.transforms[i], tr),
+            ),
+            None => tr,
+        };
+
+        // The anchor placed against the tiles on the path, from the last one
+        // back to the first one further than `reach` from it, which is where
+        // the tiles start being placed against the anchor.
+        let n = path.len();
+        let mut anchor = vec![Matrix3::one()];
+        while anchor.len() <= n && anchor[anchor.len() - 1].z.z <= reach.cosh() {
+            let j = n - anchor.len();
+            anchor.push(steps[path[j] as usize] * anchor[anchor.len() - 1]);
+        }
+        let first = n + 1 - anchor.len();
+        // Transform placing the tiles of layer `j + 1` against the tile they
+        // are placed against, from how those of layer `j` are placed.
+        let change = |j: usize| {
+            let back = |j: usize| lorentz_inverse(steps[path[j] as usize]);
+            match (j + 1).cmp(&first) {
+                Ordering::Less => back(j),
+                Ordering::Equal => lorentz_inverse(anchor[n - first]) * back(j),
+                Ordering::Greater => Matrix3::one(),
+            }
+        };
+
+        let mut nodes = vec![Node {
+            parent: None,
+            id: 0,
+            corners: if self.apeirogonal {
+                fill_corners(vec![None; self.sides], slots)
+            } else {
+                Vec::new()
+            },
+            transform: if first == 0 {
+                lorentz_inverse(anchor[n])
+            } else {
+                Matrix3::one()
+            },
+        }];
+        let mut near = Vec::new();
+        let mut layer = 0..1;
+        for j in 0.. {
+            if j >= first {
+                near.extend(layer.clone().filter(|&i| {
+                    let c = nodes[i].transform * Vector3::unit_z();
+                    c.z <= reach.cosh() * (1.0 + 1e-9)
+                }));
+            }
+            let start = nodes.len();
+            let (change, placed_on_path) = (change(j), j + 1 < first);
+            // Centre of the tile of the next layer on the path, if it is placed
+            // against the anchor.
+            let on_path = (first..n)
+                .contains(&j)
+                .then(|| lorentz_inverse(anchor[n - j - 1]).z);
+            for parent in layer {
+                let node = nodes[parent].clone();
+                let data = &self.data[node.id as usize];
+                for i in (0..self.sides).filter(|&i| node.id == 0 || i != 0) {
+                    if data.branch[i] == 0 {
+                        continue;
+                    }
+                    let transform = change * node.transform * steps[i];
+                    let c = transform * Vector3::unit_z();
+                    let kept = if placed_on_path {
+                        c.z <= stray.cosh()
+                    } else {
+                        c.z <= (reach + stray).cosh()
+                            || on_path.is_some_and(|v| cosh_distance(v, c) <= stray.cosh())
+                    };
+                    if !kept {
+                        continue;
+                    }
+                    nodes.push(Node {
+                        parent: Some((parent, i as u16)),
+                        id: data.branch[i] - 1,
+                        corners: if self.apeirogonal {
+                            child_corners(&node.corners, i, slots)
+                        } else {
+                            Vec::new()
+                        },
+                        // Those are placed against a different tile in every
+                        // layer, which would build up rounding errors.
+                        transform: if placed_on_path {
+                            snap(transform)
+                        } else {
+                            transform
+                        },
+                    });
+                }
+            }
+            layer = start..nodes.len();
+            if layer.is_empty() {
+                break;
+            }
+        }
+        (nodes, near)
+    }
+
+    /// Finds the tiles of the fragment tree standing for the tiles of the
+    /// mesh with the transforms `transforms`.
+    fn find_anchored(
+        &self,
+        transforms: &[Matrix3<f64>],
+        slots: usize,
+    ) -> (Vec<Node>, Vec<Option<Found>>) {
+        let inverse = self.turned(Matrix3::one(), self.anchor.turn) * lorentz_inverse(self.origin);
+        let placed: Vec<_> = transforms.iter().map(|&tr| inverse * tr).collect();
+        let reach = placed.iter().map(|tr| tr.z.z).fold(1.0, f64::max).acosh();
+        let (nodes, near) = self.around(&self.anchor.path, reach, slots);
+        let centres: Vec<_> = near
+            .iter()
+            .map(|&i| nodes[i].transform * Vector3::unit_z())
+            .collect();
+        let lookup = Centres::new(&centres, self.threshold());
+        let found = placed
+            .iter()
+            .map(|&tr| {
+                let node = near[lookup.find(tr * Vector3::unit_z()).next()?];
+                let turn = self.turn_between(nodes[node].transform, tr);
+                let corners = &nodes[node].corners;
+                Some(Found {
+                    id: nodes[node].id,
+                    corners: (0..corners.len())
+                        .map(|k| corners[(k + turn) % corners.len()])
+                        .collect(),
+                    turn,
+                    node,
+                })
+            })
+            .collect();
+        (nodes, found)
+    }
+
+    /// Whether the tiles are coloured by how the walk reached them.
+    pub(super) fn walk_coloured(&self) -> bool {
+        self.tiles
+            .iter()
+            .any(|tile| !matches!(tile.paint, Paint::Slot(_)))
+    }
+
+    /// Moves the anchor to the tile the camera has been moved back over by
+    /// `symmetry`, returning `false` if that tile could not be found.
+    pub(super) fn move_anchor(&mut self, symmetry: Matrix3<f64>, slots: usize) -> bool {
+        let moved = symmetry * self.origin;
+        let (nodes, found) = self.find_anchored(&[moved], slots);
+        let Some(found) = found.into_iter().next().flatten() else {
+            self.anchor = Anchor::default();
+            return false;
+        };
+        let mut path = Vec::new();
+        let mut node = found.node;
+        while let Some((parent, side)) = nodes[node].parent {
+            path.push(side);
+            node = parent;
+        }
+        path.reverse();
+        self.anchor = Anchor {
+            path,
+            turn: found.turn,
+        };
+        true
+    }
+
+    /// Colours the instances of `mesh`, generated by the walk from the origin,
+    /// as the tiles they stand for around the anchor. Instances whose tiles
+    /// are not found keep their colours.
+    pub(super) fn paint_anchored(&self, mesh: &mut InstancedMesh) {
+        if self.anchor.is_origin() || !self.walk_coloured() {
+            return;
+        }
+        let slots = mesh.palette.len();
+        let transforms: Vec<_> = mesh.placed.iter().map(|p| p.transform).collect();
+        let (_, found) = self.find_anchored(&transforms, slots);
+        for (part, tile) in mesh.parts.iter().zip(&self.tiles) {
+            for i in part.instances.clone() {
+                let Some(found) = &found[i as usize] else {
+                    continue;
+                };
+                let slot = match tile.paint {
+                    Paint::Fragment => found.id as usize,
+                    Paint::Slot(slot) => slot,
+                    Paint::Corner(corner) => found.corners[corner],
+                };
+                mesh.instances[i as usize].color = (slot % slots) as u32;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiling::{minkowski, Tiling};
+    use crate::{lorentz_orthonormalize, COLORS};
+
+    #[test]
+    fn colours_follow_the_camera() {
+        for (p, q) in [(Some(4), Some(5)), (Some(7), Some(3)), (None, Some(4))] {
+            let mut tiling = TilingGenerator::try_from_schlafli(p, q).unwrap();
+            let reference = tiling.generate(COLORS, 8);
+            let symmetries = tiling.neighbours().symmetries;
+            let mut mesh = tiling.generate(COLORS, 2);
+            // A xorshift generator, so that the path is the same every run.
+            let mut state = 0x2545_f491_4f6c_dd1d_u64;
+            let mut recentred = Matrix3::one();
+            for _ in 0..5 {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let symmetry = symmetries[state as usize % symmetries.len()];
+                recentred = lorentz_orthonormalize(recentred * symmetry);
+                assert!(tiling.recentre(&mut mesh, symmetry));
+                let transforms: Vec<_> = mesh.placed.iter().map(|p| p.transform).collect();
+                let (_, found) = tiling.find_anchored(&transforms, mesh.palette.len());
+                assert!(found.iter().all(Option::is_some), "{:?} {:?}", p, q);
+            }
+            // Every tile takes the colour of the tile it stands for in the
+            // mesh around the first tile, found at a point inside the first
+            // triangle of its prototile.
+            for part in &mesh.parts {
+                let t = &mesh.index[part.indices.start as usize..][..3];
+                let v: Vector3<f64> = t
+                    .iter()
+                    .map(|&i| Vector3::from(mesh.vertex[i as usize].pos).cast().unwrap())
+                    .sum();
+                let v = v / (-minkowski(v, v)).sqrt();
+                for i in part.instances.clone() {
+                    let moved = recentred * mesh.placed[i as usize].transform * v;
+                    let j = reference.pick(moved).unwrap();
+                    assert_eq!(
+                        mesh.instances[i as usize].color, reference.instances[j].color,
+                        "{:?} {:?} at {:?}",
+                        p, q, tiling.anchor
+                    );
+                }
+            }
+        }
+    }
+}
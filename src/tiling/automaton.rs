@@ -0,0 +1,243 @@
+//! Derivation of fragment tables from the Schläfli symbol.
+//!
+//! Tiles are placed in layers, each layer consisting of the tiles that share
+//! an edge with the region covered so far. A new tile covers a run of boundary
+//! edges joined by vertices that are missing exactly one tile, and is attached
+//! to the tile across the first edge of the run. Its subtree is determined by
+//! the length of the run and the number of placed tiles around the vertices
+//! at either end, so these become the rows of the table.
+//!
+//! Since the boundary grows exponentially, only a window of it is followed
+//! once it gets long. Covering a tile only depends on its close neighbours,
+//! so the tiles near the ends of the window are dropped at every step.
+use std::collections::HashMap;
+
+use super::Fragment;
+
+/// Number of tiles in a layer beyond which only a window of it is followed.
+const WINDOW: usize = 1 << 12;
+
+/// Number of tiles dropped at either end of the window at every step.
+const MARGIN: usize = 8;
+
+/// Number of consecutive layers that must introduce no new row before the
+/// table is considered complete.
+const SETTLE: usize = 3;
+
+/// Upper bound on the number of layers to explore.
+const MAX_LAYERS: usize = 256;
+
+/// Run length and the number of placed tiles around its start and end.
+type Key = (usize, usize, usize);
+
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    /// Number of placed tiles around the starting vertex.
+    count: usize,
+    tile: usize,
+    side: usize,
+}
+
+/// Boundary vertex in a doubly-linked list, owning the edge that starts from
+/// it.
+struct Node {
+    edge: Edge,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+/// Covers the boundary with a layer of tiles and returns the new boundary.
+fn grow(p: usize, q: usize, layer: &[Key], cyclic: bool) -> Option<Vec<Edge>> {
+    let mut nodes = Vec::new();
+    for (tile, &(k, a, _)) in layer.iter().enumerate() {
+        let m = p.checked_sub(k).filter(|&m| m != 0)?;
+        for side in 1..=m {
+            let count = if side == 1 { a + 2 } else { 1 };
+            nodes.push(Node {
+                edge: Edge { count, tile, side },
+                prev: None,
+                next: None,
+            });
+        }
+    }
+    let len = nodes.len();
+    for (i, node) in nodes.iter_mut().enumerate() {
+        if cyclic || i != 0 {
+            node.prev = Some((i + len - 1) % len);
+        }
+        if cyclic || i + 1 != len {
+            node.next = Some((i + 1) % len);
+        }
+    }
+
+    // A vertex surrounded by `q` tiles is no longer on the boundary, and the
+    // two edges meeting there are the same edge seen from either side.
+    let mut alive = vec![true; len];
+    let mut remaining = len;
+    let mut pending: Vec<usize> = (0..len).filter(|&i| nodes[i].edge.count >= q).collect();
+    while let Some(v) = pending.pop() {
+        if !alive[v] {
+            continue;
+        }
+        if nodes[v].edge.count > q || remaining <= 3 {
+            return None;
+        }
+        let (pv, nv) = match (nodes[v].prev, nodes[v].next) {
+            (Some(pv), Some(nv)) => (pv, nv),
+            // The other edge lies outside the window, so the rest of this
+            // end is unreliable anyway.
+            (None, next) => {
+                alive[v] = false;
+                remaining -= 1;
+                if let Some(nv) = next {
+                    nodes[nv].prev = None;
+                }
+                continue;
+            }
+            (prev, None) => {
+                alive[v] = false;
+                remaining -= 1;
+                if let Some(pv) = prev {
+                    nodes[pv].next = None;
+                }
+                continue;
+            }
+        };
+        nodes[nv].edge.count += nodes[pv].edge.count;
+        let ppv = nodes[pv].prev;
+        nodes[nv].prev = ppv;
+        if let Some(ppv) = ppv {
+            nodes[ppv].next = Some(nv);
+        }
+        alive[v] = false;
+        alive[pv] = false;
+        remaining -= 2;
+        if nodes[nv].edge.count >= q {
+            pending.push(nv);
+        }
+    }
+
+    let start = if cyclic {
+        alive.iter().position(|&a| a)?
+    } else {
+        (0..len).find(|&i| alive[i] && nodes[i].prev.is_none())?
+    };
+    let mut boundary = Vec::with_capacity(remaining);
+    let mut i = Some(start);
+    while let Some(n) = i {
+        boundary.push(nodes[n].edge);
+        i = nodes[n].next.filter(|&n| n != start);
+    }
+    Some(boundary)
+}
+
+/// Splits the boundary into runs of edges covered by a single tile, returning
+/// the key and first edge of each. Runs cut off by the ends of a window are
+/// left out.
+fn runs(q: usize, boundary: &[Edge], cyclic: bool) -> Option<Vec<(Key, usize)>> {
+    let len = boundary.len();
+    let start = boundary.iter().position(|e| e.count != q - 1)?;
+    let end = if cyclic { start + len } else { len };
+    let mut runs = Vec::new();
+    let mut i = start;
+    while i < end {
+        let mut k = 1;
+        while i + k < end && boundary[(i + k) % len].count == q - 1 {
+            k += 1;
+        }
+        if !cyclic && i + k == end {
+            break;
+        }
+        let key = (k, boundary[i % len].count, boundary[(i + k) % len].count);
+        runs.push((key, i % len));
+        i += k;
+    }
+    Some(runs)
+}
+
+/// Derives the fragment table of the {`p`,`q`} tiling.
+pub(super) fn derive(p: usize, q: usize) -> Option<Vec<Fragment>> {
+    let mut rows: HashMap<Key, usize> = HashMap::new();
+    let mut table: Vec<Option<Vec<Option<Key>>>> = vec![None];
+
+    // The origin's boundary is special in that every one of its edges is a
+    // run of its own.
+    let mut layer: Vec<Option<Key>> = vec![None];
+    let mut boundary: Vec<Edge> = (0..p)
+        .map(|side| Edge {
+            count: 1,
+            tile: 0,
+            side,
+        })
+        .collect();
+    let mut cyclic = true;
+
+    let mut settled = 0;
+    for _ in 0..MAX_LAYERS {
+        let runs = runs(q, &boundary, cyclic)?;
+        let mut children = vec![vec![None; p]; layer.len()];
+        for &(key, first) in &runs {
+            let edge = boundary[first];
+            children[edge.tile][edge.side] = Some(key);
+        }
+
+        // Tiles near the ends of a window may be missing some of their
+        // children, and their neighbours may be affected by that.
+        let reliable = if cyclic {
+            0..layer.len()
+        } else {
+            MARGIN..layer.len().saturating_sub(MARGIN)
+        };
+
+        // Every tile sharing a key must branch the same way.
+        let known = rows.len();
+        for t in reliable.clone() {
+            let row = match layer[t] {
+                None => 0,
+                Some(key) => *rows.entry(key).or_insert_with(|| {
+                    table.push(None);
+                    table.len() - 1
+                }),
+            };
+            match &table[row] {
+                Some(c) if *c != children[t] => return None,
+                Some(_) => {}
+                None => table[row] = Some(children[t].clone()),
+            }
+        }
+
+        let next: Vec<Key> = runs
+            .iter()
+            .filter(|&&(_, first)| reliable.contains(&boundary[first].tile))
+            .map(|&(key, _)| key)
+            .collect();
+        if rows.len() == known && next.iter().all(|key| rows.contains_key(key)) {
+            settled += 1;
+            if settled == SETTLE {
+                return table
+                    .into_iter()
+                    .map(|children| {
+                        let branch = children?
+                            .iter()
+                            .map(|c| c.map_or(Some(0), |key| rows.get(&key).map(|&r| r as u16 + 1)))
+                            .collect::<Option<_>>()?;
+                        Some(Fragment { branch })
+                    })
+                    .collect();
+            }
+        } else {
+            settled = 0;
+        }
+
+        let next = if next.len() > WINDOW {
+            cyclic = false;
+            let skip = (next.len() - WINDOW) / 2;
+            &next[skip..skip + WINDOW]
+        } else {
+            &next[..]
+        };
+        boundary = grow(p, q, next, cyclic)?;
+        layer = next.iter().copied().map(Some).collect();
+    }
+    None
+}
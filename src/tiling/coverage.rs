@@ -0,0 +1,158 @@
+//! Checking that a fragment table covers the plane exactly once.
+use cgmath::{Matrix3, Vector3};
+
+use super::{forward, rotation, TilingGenerator};
+
+/// Relative tolerance under which two tile centres are considered to be the
+/// same point.
+const EPSILON: f64 = 1e-6;
+
+/// Result of walking a fragment table, see [`TilingGenerator::coverage`].
+#[derive(Debug, Clone)]
+pub struct Coverage {
+    /// Centres of the emitted tiles on the hyperboloid, in emission order.
+    pub centres: Vec<Vector3<f64>>,
+    /// Pairs of emitted tiles sharing the same centre.
+    pub duplicates: Vec<(usize, usize)>,
+    /// Emitted tile and side whose neighbour lies within the radius but was
+    /// never emitted.
+    pub gaps: Vec<(usize, usize)>,
+}
+impl Coverage {
+    /// Whether every tile within the radius was emitted exactly once.
+    pub fn is_exact(&self) -> bool {
+        self.duplicates.is_empty() && self.gaps.is_empty()
+    }
+}
+
+/// Hyperbolic cosine of the distance between two points on the hyperboloid.
+pub(super) fn cosh_distance(u: Vector3<f64>, v: Vector3<f64>) -> f64 {
+    u.z * v.z - u.x * v.x - u.y * v.y
+}
+
+/// Emitted centres sorted by their distance from the origin, for finding
+/// coinciding ones.
+pub(super) struct Centres<'a> {
+    centres: &'a [Vector3<f64>],
+    order: Vec<usize>,
+    /// Tiles closer than this are the same tile, as distinct tiles are at
+    /// least a tile width apart.
+    threshold: f64,
+}
+impl<'a> Centres<'a> {
+    pub(super) fn new(centres: &'a [Vector3<f64>], threshold: f64) -> Self {
+        let mut order: Vec<usize> = (0..centres.len()).collect();
+        order.sort_by(|&a, &b| centres[a].z.total_cmp(&centres[b].z));
+        Centres {
+            centres,
+            order,
+            threshold,
+        }
+    }
+
+    /// Iterates over the emitted tiles centred at `v`.
+    pub(super) fn find(&self, v: Vector3<f64>) -> impl Iterator<Item = usize> + '_ {
+        let tolerance = EPSILON * v.z;
+        let start = self
+            .order
+            .partition_point(|&i| self.centres[i].z < v.z - tolerance);
+        self.order[start..]
+            .iter()
+            .take_while(move |&&i| self.centres[i].z <= v.z + tolerance)
+            .copied()
+            .filter(move |&i| cosh_distance(self.centres[i], v) < self.threshold)
+    }
+}
+
+impl TilingGenerator {
+    /// Walks the fragment table up to `depth` and reports tiles emitted more
+    /// than once, as well as neighbours of emitted tiles that lie within
+    /// `radius` of the origin but are missing.
+    pub fn coverage(&self, depth: usize, radius: f64) -> Coverage {
+        let mut centres = Vec::new();
+        let mut transforms = Vec::new();
+        self.walk(depth, |_, tr: Matrix3<f64>| {
+            centres.push(tr * Vector3::unit_z());
+            transforms.push(tr);
+        });
+
+        let threshold = (0.5 * self.len.asinh()).cosh();
+        let lookup = Centres::new(&centres, threshold);
+
+        let mut duplicates = Vec::new();
+        for (i, &c) in centres.iter().enumerate() {
+            duplicates.extend(lookup.find(c).filter(|&j| j > i).map(|j| (i, j)));
+        }
+
+        let rt = rotation(self.sides);
+        let steps: Vec<Matrix3<f64>> = (0..self.sides)
+            .scan(forward(self.len), |tr, _| {
+                let tr1 = *tr;
+                *tr = rt * tr1;
+                Some(tr1)
+            })
+            .collect();
+        let mut gaps = Vec::new();
+        for (i, tr) in transforms.iter().enumerate() {
+            for (side, step) in steps.iter().enumerate() {
+                let c = tr * step * Vector3::unit_z();
+                if c.z <= radius.cosh() && lookup.find(c).next().is_none() {
+                    gaps.push((i, side));
+                }
+            }
+        }
+
+        Coverage {
+            centres,
+            duplicates,
+            gaps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Radius that a breadth-first table of the given depth is sure to
+    /// cover.
+    fn radius(tiling: &TilingGenerator, depth: usize) -> f64 {
+        tiling.len.asinh() * depth as f64 / 4.0
+    }
+
+    #[test]
+    fn hand_written_table() {
+        let tiling =
+            TilingGenerator::try_new(Some(4), Some(5), include_str!("../4,5-tiling.txt")).unwrap();
+        let coverage = tiling.coverage(6, radius(&tiling, 6));
+        assert!(
+            coverage.is_exact(),
+            "{:?}",
+            (coverage.duplicates, coverage.gaps)
+        );
+    }
+
+    #[test]
+    fn derived_tables() {
+        for (p, q) in [(3, 7), (4, 5), (5, 4), (7, 3), (6, 6), (3, 12)] {
+            let tiling = TilingGenerator::try_from_schlafli(Some(p), Some(q)).unwrap();
+            let coverage = tiling.coverage(5, radius(&tiling, 5));
+            assert!(coverage.is_exact(), "{{{},{}}}", p, q);
+        }
+    }
+
+    #[test]
+    fn detects_duplicates() {
+        let tiling = TilingGenerator::try_new(Some(4), Some(5), "1,1,1,1\n-,1,1,1").unwrap();
+        let coverage = tiling.coverage(4, 0.0);
+        assert!(!coverage.duplicates.is_empty());
+    }
+
+    #[test]
+    fn detects_gaps() {
+        let tiling = TilingGenerator::try_new(Some(4), Some(5), "1,1,1,1\n-,-,1,-").unwrap();
+        let coverage = tiling.coverage(4, 2.0 * tiling.len.asinh());
+        assert!(coverage.duplicates.is_empty());
+        assert!(!coverage.gaps.is_empty());
+    }
+}
@@ -0,0 +1,269 @@
+//! Tilings with ideal vertices, {p,∞}, and their duals, {∞,q}.
+//!
+//! The tiles of {p,∞} only meet along their sides, so they form a tree in
+//! which each tile is reached from the one it shares side 0 with. A tile of
+//! {∞,p} is centred on an ideal vertex of {p,∞}, and each tile of {p,∞}
+//! contains the piece of it between the tile's centre and the midpoints of
+//! the sides meeting at that vertex.
+use cgmath::{InnerSpace, Matrix3, Vector2};
+
+use super::{
+    dimensions, forward, generate_fan, lift, polygon_corners, rotation, Fragment, Paint, Prototile,
+    TilingGenerator,
+};
+
+/// Fragment table of {`sides`,∞}, in which every tile but the first one
+/// continues across all of its sides except the one facing back.
+pub(super) fn tree(sides: usize) -> Vec<Fragment> {
+    vec![
+        Fragment {
+            branch: vec![2; sides],
+        },
+        Fragment {
+            branch: (0..sides).map(|i| if i == 0 { 0 } else { 2 }).collect(),
+        },
+    ]
+}
+
+/// Pieces of the apeirogons of {∞,`sides`} within a tile of {`sides`,∞},
+/// one for each corner of the tile and painted by it.
+pub(super) fn apeirogon_pieces(sides: usize, subdiv: usize) -> Vec<Prototile> {
+    let (side, _) = dimensions(sides, None);
+    let corners = polygon_corners(sides, side);
+    (0..sides)
+        .map(|k| {
+            // Corner `k` lies between sides `k` and `k + 1`.
+            let corner = corners[k];
+            let piece = [
+                Vector2::new(0.0, 0.0),
+                (corners[(k + sides - 1) % sides] + corner) / 2.0,
+                corner,
+                (corner + corners[(k + 1) % sides]) / 2.0,
+            ];
+            let centre = piece.iter().sum::<Vector2<f64>>() / piece.len() as f64;
+            Prototile {
+                mesh: generate_fan(centre, &piece, subdiv),
+                paint: Paint::Corner(k),
+                // The apeirogon is centred on the ideal corner.
+                centre: corner.normalize().extend(1.0),
+                corners: piece.iter().map(|&v| lift(v)).collect(),
+            }
+        })
+        .collect()
+}
+
+/// Gives a slot out of `slots` to the corners that don't have one yet,
+/// different from those of the other corners where possible, and otherwise
+/// from those of the neighbouring corners.
+pub(super) fn fill_corners(mut corners: Vec<Option<usize>>, slots: usize) -> Vec<usize> {
+    let n = corners.len();
+    for k in 0..n {
+        if corners[k].is_some() {
+            continue;
+        }
+        let neighbours = [corners[(k + n - 1) % n], corners[(k + 1) % n]];
+        let slot = (0..slots)
+            .find(|&s| !corners.contains(&Some(s)))
+            .or_else(|| (0..slots).find(|&s| !neighbours.contains(&Some(s))))
+            .unwrap_or(0);
+        corners[k] = Some(slot);
+    }
+    corners.into_iter().flatten().collect()
+}
+
+/// Slots of the corners of the tile across side `side` of a tile whose
+/// corners have the slots `corners`.
+pub(super) fn child_corners(corners: &[usize], side: usize, slots: usize) -> Vec<usize> {
+    let sides = corners.len();
+    // The child's side 0 is side `side`, running the other way.
+    let mut child = vec![None; sides];
+    child[0] = Some(corners[(side + sides - 1) % sides]);
+    child[sides - 1] = Some(corners[side]);
+    fill_corners(child, slots)
+}
+
+struct State<'a, F> {
+    steps: Vec<Matrix3<f64>>,
+    data: &'a [Fragment],
+    slots: usize,
+    /// Sides crossed on the way to the current tile.
+    address: Vec<u16>,
+    push: F,
+}
+
+fn layer<F: FnMut(&[u16], u16, &[usize], Matrix3<f64>)>(
+    state: &mut State<F>,
+    tr: Matrix3<f64>,
+    id: u16,
+    corners: &[usize],
+    layers: usize,
+) {
+    (state.push)(&state.address, id, corners, tr);
+    if layers == 0 {
+        return;
+    }
+    let sides = corners.len();
+    for i in (0..sides).filter(|&i| id == 0 || i != 0) {
+        let next = state.data[id as usize].branch[i];
+        if next == 0 {
+            continue;
+        }
+        let child = child_corners(corners, i, state.slots);
+        state.address.push(i as u16);
+        layer(state, tr * state.steps[i], next - 1, &child, layers - 1);
+        state.address.pop();
+    }
+}
+
+impl TilingGenerator {
+    /// Like [`TilingGenerator::walk_addressed`], but also passes a slot out of
+    /// `slots` for each corner of the tile, such that tiles sharing a corner
+    /// give it the same slot and neighbouring corners get different slots.
+    ///
+    /// Only tiles sharing a side with their parent inherit its slots, so the
+    /// tiles sharing a corner must be reached from one another, as is the
+    /// case for the ideal vertices of {p,∞}.
+    pub(super) fn walk_corners<F: FnMut(&[u16], u16, &[usize], Matrix3<f64>)>(
+        &self,
+        depth: usize,
+        slots: usize,
+        push: F,
+    ) {
+        let rt = rotation(self.sides);
+        let steps = (0..self.sides)
+            .scan(forward(self.len), |tr, _| {
+                let tr1 = *tr;
+                *tr = rt * tr1;
+                Some(tr1)
+            })
+            .collect();
+        let mut state = State {
+            steps,
+            data: &self.data,
+            slots,
+            address: Vec::new(),
+            push,
+        };
+        let corners = fill_corners(vec![None; self.sides], slots);
+        layer(&mut state, self.origin, 0, &corners, depth);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lorentz_inverse;
+    use crate::tiling::{kleinpoint, minkowski, Tiling, TilingError};
+
+    #[test]
+    fn vertices_go_to_infinity() {
+        for p in 3..9 {
+            let ideal = TilingGenerator::try_from_schlafli(Some(p), None).unwrap();
+            let ideal = ideal.vertices();
+            for v in &ideal {
+                assert!(v.z > 0.0 && minkowski(*v, *v).abs() < 1e-12, "{:?}", v);
+            }
+            // The corners of {p,q} run off along the ideal ones as q grows,
+            // which are on the boundary of the Klein disc.
+            let mut height = 1.0;
+            for q in [8, 16, 64, 1024] {
+                let corners = polygon_corners(p, dimensions(p, Some(q)).0);
+                let z = kleinpoint(corners[0]).z;
+                assert!(z > height);
+                height = z;
+                for (u, v) in corners.iter().zip(&ideal) {
+                    let v = v.truncate() / v.z;
+                    assert!((u - v).magnitude() < 4.0 / z, "{{{},{}}}", p, q);
+                }
+            }
+        }
+    }
+
+    /// Number of tiles of a tree in which the first tile has `sides`
+    /// children and every other one has one less.
+    fn tree_size(sides: usize, depth: usize) -> usize {
+        (0..depth)
+            .fold((1, sides), |(n, layer), _| (n + layer, layer * (sides - 1)))
+            .0
+    }
+
+    #[test]
+    fn branching() {
+        for sides in 3..7 {
+            let ideal = TilingGenerator::try_from_schlafli(Some(sides), None).unwrap();
+            let apeirogonal = TilingGenerator::try_from_schlafli(None, Some(sides)).unwrap();
+            for depth in 0..5 {
+                assert_eq!(ideal.transforms(depth).len(), tree_size(sides, depth));
+                assert_eq!(apeirogonal.transforms(depth).len(), tree_size(sides, depth));
+                // Each walked tile holds a piece of each apeirogon around it.
+                let mut pieces = 0;
+                apeirogonal.walk_corners(depth, 3, |_, _, corners, _| {
+                    assert_eq!(corners.len(), sides);
+                    pieces += corners.len();
+                });
+                assert_eq!(pieces, sides * tree_size(sides, depth));
+            }
+        }
+    }
+
+    #[test]
+    fn neighbouring_corners_differ() {
+        for sides in 3..7 {
+            let tiling = TilingGenerator::try_from_schlafli(None, Some(sides)).unwrap();
+            tiling.walk_corners(4, 3, |_, _, corners, _| {
+                for k in 0..sides {
+                    assert_ne!(corners[k], corners[(k + 1) % sides], "{:?}", corners);
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn zero_is_not_infinite() {
+        for (p, q) in [(Some(0), Some(5)), (Some(5), Some(0)), (None, Some(0))] {
+            assert!(matches!(
+                TilingGenerator::try_from_schlafli(p, q),
+                Err(TilingError::NotHyperbolic { .. })
+            ));
+        }
+        assert!(matches!(
+            TilingGenerator::try_from_schlafli(None, None),
+            Err(TilingError::Unsupported { .. })
+        ));
+    }
+
+    #[test]
+    fn pieces_of_an_apeirogon_share_it() {
+        let tiling = TilingGenerator::try_from_schlafli(None, Some(4)).unwrap();
+        let mesh = tiling.generate(crate::COLORS, 4);
+        let transforms = tiling.transforms(4);
+        let (side, _) = dimensions(4, None);
+        let corners: Vec<_> = polygon_corners(4, side)
+            .into_iter()
+            .map(|c| c.normalize())
+            .collect();
+        for (k, part) in mesh.parts.iter().enumerate() {
+            let root = part.instances.start as usize;
+            let face = mesh.placed[root].face;
+            let shared: Vec<_> = (0..mesh.placed.len())
+                .filter(|&i| mesh.placed[i].face == face)
+                .collect();
+            for &i in &shared {
+                assert_eq!(mesh.instances[i].color, mesh.instances[root].color);
+            }
+            // Every tile with the ideal corner the apeirogon is centred on
+            // has a piece of it.
+            let ideal = corners[k].extend(1.0);
+            let tiles = transforms
+                .iter()
+                .filter(|&&tr| {
+                    let v = lorentz_inverse(tr) * ideal;
+                    let v = v.truncate() / v.z;
+                    corners.iter().any(|&c| (c - v).magnitude() < 1e-9)
+                })
+                .count();
+            assert!(tiles > 2);
+            assert_eq!(shared.len(), tiles);
+        }
+    }
+}
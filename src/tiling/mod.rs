@@ -0,0 +1,1174 @@
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+use std::fmt;
+use std::str::FromStr;
+
+use cgmath::{BaseFloat, InnerSpace, Matrix2, Matrix3, One, Rad, Vector2, Vector3, VectorSpace};
+use log::warn;
+use wasm_bindgen::prelude::*;
+
+use crate::palette::PALETTE_SIZE;
+use crate::{lorentz_inverse, translation, Color, Instance, InstancedMesh, Part, Placed, Vertex};
+
+mod anchor;
+mod automaton;
+mod coverage;
+mod ideal;
+mod triangle;
+mod wythoff;
+
+pub use coverage::Coverage;
+pub use triangle::TriangleTiling;
+
+/// Entry of a Schläfli symbol, with `None` standing for ∞. Written as a
+/// number, or as `∞` or `inf`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Order(pub Option<usize>);
+impl fmt::Display for Order {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            None => f.write_str("∞"),
+            Some(n) => write!(f, "{}", n),
+        }
+    }
+}
+impl FromStr for Order {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "∞" | "inf" => Ok(Order(None)),
+            s => s.parse().map(|n| Order(Some(n))),
+        }
+    }
+}
+
+/// A tiling of the hyperbolic plane that can be turned into a mesh.
+pub trait Tiling {
+    /// Generates the tiles up to `depth` steps away from the origin, coloured
+    /// from `colors`.
+    fn generate(&self, colors: &[Color], depth: usize) -> InstancedMesh;
+
+    /// Returns the tile around the origin as a domain whose mirror images
+    /// make up the tiling, if it can be drawn that way.
+    fn domain(&self) -> Option<Domain>;
+
+    /// Returns the symmetries taking the tile around the origin to the tiles
+    /// around it, for keeping the camera over that tile.
+    fn neighbours(&self) -> Neighbours;
+
+    /// Returns the corners of each prototile of the meshes it generates, in
+    /// order around it on the hyperboloid, with ideal corners given as
+    /// lightlike vectors.
+    fn prototile_corners(&self) -> Vec<Vec<Vector3<f64>>>;
+
+    /// Recolours the tiles of `mesh`, generated by this tiling, as the tiles
+    /// they stand for once the camera has been moved back by `symmetry`, one
+    /// of the [`Neighbours`], and keeps doing so for the meshes it generates.
+    /// Returns `false` if the colours are left as they were.
+    fn recentre(&mut self, mesh: &mut InstancedMesh, symmetry: Matrix3<f64>) -> bool;
+}
+
+/// Amount by which a neighbouring centre must be nearer to a point than the
+/// centre of the tile around the origin, as the cosh of the distance, to be
+/// moved to, so that points halfway between are left alone.
+const NEIGHBOUR_MARGIN: f64 = 1e-9;
+
+/// Symmetries of a tiling taking the tile around the origin to the tiles
+/// around it.
+///
+/// Composing the camera's transform with one of them moves the camera back
+/// over the tile around the origin without moving the tiles it sees, so the
+/// view can wander indefinitely over a mesh of fixed depth. Tiles coloured by
+/// how the walk reached them keep their colours, see [`Tiling::recentre`].
+#[derive(Debug, Clone)]
+pub struct Neighbours {
+    /// Centre of the tile around the origin.
+    pub centre: Vector3<f64>,
+    pub symmetries: Vec<Matrix3<f64>>,
+}
+impl Neighbours {
+    /// Returns the symmetry taking the centre to the neighbouring centre
+    /// nearest to `v`, if `v` is nearer to it than to the centre.
+    pub fn nearest(&self, v: Vector3<f64>) -> Option<Matrix3<f64>> {
+        let own = -minkowski(self.centre, v);
+        self.symmetries
+            .iter()
+            .map(|&g| (-minkowski(g * self.centre, v), g))
+            .filter(|&(cosh, _)| cosh < own - NEIGHBOUR_MARGIN)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, g)| g)
+    }
+}
+
+/// How the tiles of a [`Domain`] are coloured.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DomainPaint {
+    /// By the parity of the number of reflections, for domains whose mirror
+    /// images never overlap themselves.
+    Parity,
+    /// By the number of reflections taking the centre of the tile back into
+    /// the domain.
+    Steps,
+}
+
+/// Convex polygon whose images under the reflections across its sides make
+/// up a tiling.
+#[derive(Debug, Clone)]
+pub struct Domain {
+    /// Inward-pointing unit normals of the sides.
+    pub sides: Vec<Vector3<f64>>,
+    pub centre: Vector3<f64>,
+    pub paint: DomainPaint,
+}
+
+const TURN_AROUND: Matrix3<f64> = Matrix3::new(-1.0, 0.0, 0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 1.0);
+
+struct State<'a, F> {
+    sides: usize,
+    rotation_matrix: Matrix3<f64>,
+    forward_transform: Matrix3<f64>,
+    data: &'a [Fragment],
+    /// Sides crossed on the way to the current tile.
+    address: Vec<u16>,
+    push: F,
+}
+impl<'a, F> State<'a, F> {
+    pub fn iter(&self) -> impl Iterator<Item = (usize, Matrix3<f64>)> {
+        let rt = self.rotation_matrix;
+        (0..self.sides).scan(self.forward_transform, move |tr, i| {
+            let tr1 = *tr;
+            *tr = rt * tr1;
+            Some((i, tr1))
+        })
+    }
+}
+
+fn layer<F: FnMut(&[u16], u16, Matrix3<f64>)>(
+    state: &mut State<F>,
+    tr: Matrix3<f64>,
+    id: u16,
+    layers: usize,
+) {
+    (state.push)(&state.address, id, tr);
+    if layers != 0 {
+        let data = state.data;
+        state
+            .iter()
+            .filter(|(i, _)| id == 0 || *i != 0)
+            .map(|(i, tr)| (i, data[id as usize].branch[i], tr))
+            .filter(|(_, id, _)| *id != 0)
+            .for_each(|(i, id, tr1)| {
+                state.address.push(i as u16);
+                layer(state, tr * tr1, id - 1, layers - 1);
+                state.address.pop();
+            });
+    }
+}
+
+struct Fragment {
+    branch: Vec<u16>,
+}
+impl fmt::Display for Fragment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, &b) in self.branch.iter().enumerate() {
+            if i != 0 {
+                f.write_str(",")?;
+            }
+            match b {
+                0 => f.write_str("-")?,
+                b => write!(f, "{}", b - 1)?,
+            }
+        }
+        Ok(())
+    }
+}
+impl Fragment {
+    /// Parses a row of `sides` comma-separated entries, each either `-` or
+    /// the id of the row to continue with.
+    pub fn parse(s: &str, line: usize, sides: usize) -> Result<(Self, Vec<usize>), ParseError> {
+        let mut branch = Vec::with_capacity(sides);
+        let mut columns = Vec::with_capacity(sides);
+        let mut offset = 0;
+        for token in s.split(',') {
+            let column = offset + token.chars().take_while(|c| c.is_whitespace()).count() + 1;
+            offset += token.chars().count() + 1;
+            let token = token.trim();
+            let b = match token {
+                "-" => 0,
+                _ => token
+                    .parse::<u16>()
+                    .ok()
+                    .and_then(|v| v.checked_add(1))
+                    .ok_or_else(|| ParseError {
+                        line,
+                        column,
+                        kind: ParseErrorKind::BadToken(token.to_owned()),
+                    })?,
+            };
+            branch.push(b);
+            columns.push(column);
+        }
+        if branch.len() != sides {
+            // Point at the first entry too many, or past the end of the row.
+            let column = match columns.get(sides) {
+                Some(&column) => column,
+                None => s.trim_end().chars().count() + 1,
+            };
+            return Err(ParseError {
+                line,
+                column,
+                kind: ParseErrorKind::WrongArity {
+                    expected: sides,
+                    found: branch.len(),
+                },
+            });
+        }
+        Ok((Fragment { branch }, columns))
+    }
+
+    /// Parses a whole table, one row per non-blank line.
+    pub fn parse_table(s: &str, sides: usize) -> Result<Vec<Self>, ParseError> {
+        let mut data = Vec::new();
+        let mut positions = Vec::new();
+        for (line, row) in s.lines().enumerate() {
+            if row.trim().is_empty() {
+                continue;
+            }
+            let (fragment, columns) = Fragment::parse(row, line + 1, sides)?;
+            positions.push((line + 1, columns));
+            data.push(fragment);
+        }
+        if data.is_empty() {
+            return Err(ParseError {
+                line: 1,
+                column: 1,
+                kind: ParseErrorKind::Empty,
+            });
+        }
+
+        let rows = data.len();
+        for (fragment, (line, columns)) in data.iter().zip(&positions) {
+            for (&b, &column) in fragment.branch.iter().zip(columns) {
+                if b as usize > rows {
+                    return Err(ParseError {
+                        line: *line,
+                        column,
+                        kind: ParseErrorKind::DanglingReference {
+                            id: b as usize - 1,
+                            rows,
+                        },
+                    });
+                }
+            }
+        }
+        Ok(data)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// An entry is neither `-` nor a row id.
+    BadToken(String),
+    /// A row doesn't have one entry per side of the tile.
+    WrongArity { expected: usize, found: usize },
+    /// An entry refers to a row past the end of the table.
+    DanglingReference { id: usize, rows: usize },
+    /// The table has no rows.
+    Empty,
+}
+
+/// Error in a fragment table, with 1-based line and column numbers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            ParseErrorKind::BadToken(token) => {
+                write!(f, "expected `-` or a row id, found `{}`", token)
+            }
+            ParseErrorKind::WrongArity { expected, found } => {
+                write!(f, "expected {} entries, found {}", expected, found)
+            }
+            ParseErrorKind::DanglingReference { id, rows } => {
+                write!(f, "row {} does not exist, the table has {} rows", id, rows)
+            }
+            ParseErrorKind::Empty => f.write_str("the table is empty"),
+        }
+    }
+}
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TilingError {
+    /// The Schläfli symbol doesn't describe a tiling of the hyperbolic plane.
+    NotHyperbolic {
+        p: Option<usize>,
+        q: Option<usize>,
+    },
+    /// The angles π/p, π/q and π/r don't form a hyperbolic triangle.
+    NotHyperbolicTriangle {
+        p: usize,
+        q: usize,
+        r: usize,
+    },
+    /// No fragment table could be derived for the Schläfli symbol.
+    Underivable {
+        p: usize,
+        q: usize,
+    },
+    /// The Schläfli symbol describes a tiling that can't be generated.
+    Unsupported {
+        p: Option<usize>,
+        q: Option<usize>,
+    },
+    /// The string is not a Wythoff symbol over the triangle (p,q,2).
+    BadWythoff(String),
+    Parse(ParseError),
+}
+impl fmt::Display for TilingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TilingError::NotHyperbolic { p, q } => {
+                write!(
+                    f,
+                    "{{{},{}}} is not a hyperbolic tiling",
+                    Order(*p),
+                    Order(*q)
+                )
+            }
+            TilingError::NotHyperbolicTriangle { p, q, r } => {
+                write!(f, "({},{},{}) is not a hyperbolic triangle group", p, q, r)
+            }
+            TilingError::Underivable { p, q } => {
+                write!(f, "failed to derive the fragment table of {{{},{}}}", p, q)
+            }
+            TilingError::Unsupported { p, q } => {
+                write!(f, "{{{},{}}} is not supported", Order(*p), Order(*q))
+            }
+            TilingError::BadWythoff(symbol) => write!(
+                f,
+                "`{}` is not a Wythoff symbol such as `2 q | p`, with one of the numbers being 2",
+                symbol
+            ),
+            TilingError::Parse(err) => err.fmt(f),
+        }
+    }
+}
+impl std::error::Error for TilingError {}
+impl From<ParseError> for TilingError {
+    fn from(err: ParseError) -> Self {
+        TilingError::Parse(err)
+    }
+}
+
+fn kleinpoint<S: BaseFloat>(v: Vector2<S>) -> Vector3<S> {
+    let w = S::one() / (S::one() - v.magnitude2()).sqrt();
+    v.extend(S::one()) * w
+}
+
+/// Minkowski inner product with signature (+, +, -).
+pub(crate) fn minkowski(u: Vector3<f64>, v: Vector3<f64>) -> f64 {
+    u.x * v.x + u.y * v.y - u.z * v.z
+}
+
+/// Points of the Klein disk this close to its rim are taken to be on it.
+const IDEAL_EPSILON: f64 = 1e-9;
+
+/// Distance from the nearest finite point at which a side running to an ideal
+/// point is cut off, which is within a pixel of the rim of the Poincaré disk.
+pub(crate) const IDEAL_CUTOFF: f64 = 8.0;
+
+/// Length of the segments a side running to an ideal point is split into.
+const IDEAL_STEP: f64 = 0.25;
+
+fn is_ideal(v: Vector2<f64>) -> bool {
+    v.magnitude2() > 1.0 - IDEAL_EPSILON
+}
+
+/// Point of the hyperboloid at `v` in Klein coordinates, or the lightlike
+/// vector of the ideal point there.
+fn lift(v: Vector2<f64>) -> Vector3<f64> {
+    if is_ideal(v) {
+        v.normalize().extend(1.0)
+    } else {
+        kleinpoint(v)
+    }
+}
+
+/// Parametrises the geodesic from `base` towards the ideal point `end`, both
+/// in Klein coordinates, by the distance from `base`.
+fn geodesic(base: Vector2<f64>, end: Vector2<f64>) -> impl Fn(f64) -> Vector3<f64> {
+    let b = kleinpoint(base);
+    let l = end.extend(1.0);
+    let t = l / -minkowski(l, b) - b;
+    move |s| b * s.cosh() + t * s.sinh()
+}
+
+/// Points along the side from `from` to `to`, given in Klein coordinates,
+/// starting with `from` and stopping short of `to`. Sides between finite
+/// corners are split into `subdiv` segments, while those running to ideal
+/// corners are cut off near the rim and split into segments of the same
+/// length, which concentrates points near the rim of the Poincaré disk.
+fn side_points(from: Vector2<f64>, to: Vector2<f64>, subdiv: usize) -> Vec<Vector3<f64>> {
+    let n = (IDEAL_CUTOFF / IDEAL_STEP).ceil() as usize;
+    let step = IDEAL_CUTOFF / n as f64;
+    match (is_ideal(from), is_ideal(to)) {
+        (false, false) => (0..subdiv)
+            .map(|i| kleinpoint(from.lerp(to, i as f64 / subdiv as f64)))
+            .collect(),
+        (false, true) => {
+            let g = geodesic(from, to);
+            (0..=n).map(|i| g(i as f64 * step)).collect()
+        }
+        (true, false) => {
+            let g = geodesic(to, from);
+            (0..n).map(|i| g((n - i) as f64 * step)).collect()
+        }
+        (true, true) => {
+            let g = geodesic((from + to) / 2.0, to);
+            (0..=2 * n)
+                .map(|i| g((i as f64 - n as f64) * step))
+                .collect()
+        }
+    }
+}
+
+struct Mesh<S> {
+    vertex: Vec<S>,
+    index: Vec<u32>,
+}
+impl<S> Mesh<S> {
+    fn new() -> Self {
+        Mesh {
+            vertex: Vec::new(),
+            index: Vec::new(),
+        }
+    }
+
+    /// Appends the triangles of `other`.
+    fn extend(&mut self, other: Mesh<S>) {
+        let idx = self.vertex.len() as u32;
+        self.vertex.extend(other.vertex);
+        self.index.extend(other.index.iter().map(|&i| idx + i));
+    }
+}
+/// Distance in the Poincaré disk under which the centres of faces are the
+/// same point.
+const FACE_EPSILON: f64 = 1e-9;
+
+/// Numbers the faces of a tiling by their centres in the Poincaré disk,
+/// which are on its boundary if they are ideal.
+#[derive(Default)]
+struct Faces {
+    /// Centres in the Poincaré disk, by number.
+    centres: Vec<Vector2<f64>>,
+    /// Numbers of the faces by the square of side `FACE_EPSILON` their
+    /// centres lie in.
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+impl Faces {
+    /// Returns the number of the face centred at `p`, numbering it if it is
+    /// new.
+    fn number(&mut self, p: Vector2<f64>) -> usize {
+        let cell = (
+            (p.x / FACE_EPSILON).floor() as i64,
+            (p.y / FACE_EPSILON).floor() as i64,
+        );
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let near = self.cells.get(&(cell.0 + dx, cell.1 + dy));
+                if let Some(&face) = near
+                    .into_iter()
+                    .flatten()
+                    .find(|&&face| (self.centres[face] - p).magnitude() < FACE_EPSILON)
+                {
+                    return face;
+                }
+            }
+        }
+        let face = self.centres.len();
+        self.centres.push(p);
+        self.cells.entry(cell).or_default().push(face);
+        face
+    }
+}
+
+/// Collects the copies of a set of prototiles for instanced drawing.
+struct Instances<'a> {
+    meshes: Vec<&'a Mesh<Vector3<f64>>>,
+    /// Centres of the faces the prototiles are pieces of.
+    centres: Vec<Vector3<f64>>,
+    copies: Vec<Vec<(Instance, Placed)>>,
+    faces: Faces,
+    palette: &'a [Color],
+}
+impl<'a> Instances<'a> {
+    /// Starts collecting copies of the prototiles `tiles`, each given with the
+    /// centre of the face it is a piece of.
+    fn new(tiles: Vec<(&'a Mesh<Vector3<f64>>, Vector3<f64>)>, palette: &'a [Color]) -> Self {
+        let palette = &palette[..palette.len().min(PALETTE_SIZE)];
+        let (meshes, centres): (Vec<_>, Vec<_>) = tiles.into_iter().unzip();
+        Instances {
+            copies: vec![Vec::new(); meshes.len()],
+            meshes,
+            centres,
+            faces: Faces::default(),
+            palette,
+        }
+    }
+
+    /// Adds a copy of prototile `k` moved by `origin`, coloured from `slot`
+    /// wrapped around the palette, for the tile at `address`.
+    fn push(&mut self, k: usize, origin: Matrix3<f64>, slot: usize, address: &[u16]) {
+        // The Minkowski norm is kept by the move, and only exact before it for
+        // ideal centres.
+        let centre = self.centres[k];
+        let v = origin * centre;
+        let face = self
+            .faces
+            .number(v.truncate() / (v.z + (-minkowski(centre, centre)).max(0.0).sqrt()));
+        self.copies[k].push((
+            Instance {
+                transform: origin.cast::<f32>().unwrap().into(),
+                color: (slot % self.palette.len()) as u32,
+            },
+            Placed {
+                address: address.to_vec(),
+                transform: origin,
+                face,
+            },
+        ));
+    }
+
+    fn finish(self) -> InstancedMesh {
+        let mut mesh = InstancedMesh {
+            palette: self.palette.to_vec(),
+            ..InstancedMesh::default()
+        };
+        for (tile, copies) in self.meshes.into_iter().zip(self.copies) {
+            let idx = mesh.vertex.len() as u32;
+            let indices = mesh.index.len() as u32;
+            let instances = mesh.instances.len() as u32;
+            mesh.vertex.extend(tile.vertex.iter().map(|&v| Vertex {
+                pos: v.cast::<f32>().unwrap().into(),
+            }));
+            mesh.index.extend(tile.index.iter().map(|&i| idx + i));
+            for (instance, placed) in copies {
+                mesh.instances.push(instance);
+                mesh.placed.push(placed);
+            }
+            mesh.parts.push(Part {
+                indices: indices..mesh.index.len() as u32,
+                instances: instances..mesh.instances.len() as u32,
+            });
+        }
+        mesh
+    }
+}
+
+/// How a prototile picks its colour from the palette.
+#[derive(Debug, Clone, Copy)]
+enum Paint {
+    /// By the fragment id of the tile.
+    Fragment,
+    /// From a fixed slot.
+    Slot(usize),
+    /// From the slot given to the corner with this index by
+    /// [`TilingGenerator::walk_corners`].
+    Corner(usize),
+}
+
+/// Part of the tiling drawn along with every tile.
+struct Prototile {
+    mesh: Mesh<Vector3<f64>>,
+    paint: Paint,
+    /// Centre of the face of the tiling the part is a piece of, as a
+    /// lightlike vector if it is ideal.
+    centre: Vector3<f64>,
+    /// Corners of the part, as given by [`Tiling::prototile_corners`].
+    corners: Vec<Vector3<f64>>,
+}
+
+/// Corners of the prototile in Klein coordinates, in counterclockwise order
+/// starting with the one preceding side 0.
+fn polygon_corners(sides: usize, side: f64) -> Vec<Vector2<f64>> {
+    let central_angle = TAU / sides as f64;
+    let rotation_matrix = Matrix2::from_angle(Rad(central_angle));
+
+    let (s, c) = (0.5 * central_angle).sin_cos();
+
+    (0..sides)
+        .scan(Vector2::new(-side * c, -side * s), |v, _| {
+            let v1 = *v;
+            *v = rotation_matrix * v1;
+            Some(v1)
+        })
+        .collect()
+}
+
+/// Generate a band along the side from `from` to `to`, given in Klein
+/// coordinates, between the curves at distances `near` and `far` from it,
+/// counted positively towards `inside`.
+fn generate_band(
+    from: Vector2<f64>,
+    to: Vector2<f64>,
+    inside: Vector2<f64>,
+    (near, far): (f64, f64),
+    subdiv: usize,
+) -> Mesh<Vector3<f64>> {
+    let mut points = side_points(from, to, subdiv);
+    if !is_ideal(to) {
+        points.push(kleinpoint(to));
+    }
+    let c = from.extend(1.0).cross(to.extend(1.0));
+    let normal = Vector3::new(c.x, c.y, -c.z) / minkowski(c, c).sqrt();
+    let normal = if minkowski(normal, inside.extend(1.0)) < 0.0 {
+        -normal
+    } else {
+        normal
+    };
+
+    let vertex = points
+        .iter()
+        .flat_map(|&v| {
+            [
+                v * near.cosh() + normal * near.sinh(),
+                v * far.cosh() + normal * far.sinh(),
+            ]
+        })
+        .collect();
+    let index = (0..points.len() as u32 - 1)
+        .flat_map(|i| {
+            let j = 2 * i;
+            [j, j + 2, j + 1, j + 1, j + 2, j + 3]
+        })
+        .collect();
+
+    Mesh { vertex, index }
+}
+
+/// Generate any-sided polygon in the hyperbolic plane.
+fn generate_polygon(sides: usize, side: f64, subdiv: usize) -> Mesh<Vector3<f64>> {
+    debug_assert!((sides * subdiv).is_multiple_of(2));
+    generate_fan(
+        Vector2::new(0.0, 0.0),
+        &polygon_corners(sides, side),
+        subdiv,
+    )
+}
+
+/// Generate a fan of triangles around `centre` spanning a convex polygon,
+/// given in Klein coordinates, with each side split as by [`side_points`].
+fn generate_fan(
+    centre: Vector2<f64>,
+    corners: &[Vector2<f64>],
+    subdiv: usize,
+) -> Mesh<Vector3<f64>> {
+    let mut vertex = vec![kleinpoint(centre)];
+    for (i, &from) in corners.iter().enumerate() {
+        let to = corners[(i + 1) % corners.len()];
+        vertex.extend(side_points(from, to, subdiv));
+    }
+    let points = vertex.len() - 1;
+    let mut index = Vec::with_capacity(3 * points);
+    for i in 0..points as u32 {
+        let j = (i + 1) % points as u32;
+        index.extend_from_slice(&[0, 1 + i, 1 + j]);
+    }
+
+    Mesh { vertex, index }
+}
+
+fn check_hyperbolic(p: Option<usize>, q: Option<usize>) -> Result<(), TilingError> {
+    match (p, q) {
+        (None, None) => Err(TilingError::Unsupported { p, q }),
+        (None, Some(n)) | (Some(n), None) if n >= 3 => Ok(()),
+        (Some(p), Some(q)) if p >= 3 && q >= 3 && (p - 2) * (q - 2) > 4 => Ok(()),
+        _ => Err(TilingError::NotHyperbolic { p, q }),
+    }
+}
+
+/// Returns the side parameter of the prototile passed to [`generate_polygon`]
+/// and the translation length between neighbouring tile centres.
+fn dimensions(p: usize, q: Option<usize>) -> (f64, f64) {
+    let half_central = TAU / (2.0 * p as f64);
+    // Ideal vertices have an inner angle of zero.
+    let half_inner = match q {
+        None => 0.0,
+        Some(q) => TAU / (2.0 * q as f64),
+    };
+    let v = half_inner.cos() / half_central.sin();
+    debug_assert!(v >= 1.0);
+    let w = (v * v - 1.0).sqrt();
+    let side = w / v / half_central.cos();
+    let len = 2.0 * v * w;
+    (side, len)
+}
+
+fn rotation(sides: usize) -> Matrix3<f64> {
+    Matrix3::from_angle_z(Rad(TAU / sides as f64))
+}
+
+/// Transform from a tile to its neighbour across side 0, with the
+/// neighbour's side 0 facing back.
+fn forward(len: f64) -> Matrix3<f64> {
+    translation(Vector2::new(-len, 0.0)) * TURN_AROUND
+}
+
+/// Half the width of the edges drawn by [`TilingGenerator::generate_edges`].
+const EDGE_WIDTH: f64 = 0.02;
+
+/// Generator for {p,q} tilings, where either p or q may be ∞, given as `None`.
+///
+/// The tiles of {∞,q} have infinitely many sides, so they are drawn instead
+/// as pieces of the tiles of {q,∞}, whose ideal vertices are the centres of
+/// the apeirogons.
+#[wasm_bindgen]
+pub struct TilingGenerator {
+    len: f64,
+    /// Number of sides of the walked tiles.
+    sides: usize,
+    /// Number of walked tiles around each vertex, `None` if the vertices are
+    /// ideal.
+    valence: Option<usize>,
+    /// Whether the walked tiles are cut into the apeirogons of {∞,`sides`}.
+    apeirogonal: bool,
+    /// Transform of the tile the walk starts from.
+    origin: Matrix3<f64>,
+    /// Tile the tile around the origin stands for, once the camera has been
+    /// moved back.
+    anchor: anchor::Anchor,
+    tiles: Vec<Prototile>,
+    data: Vec<Fragment>,
+}
+#[wasm_bindgen]
+impl TilingGenerator {
+    /// Creates the generator for the {`p`,`q`} tiling from a fragment table,
+    /// throwing an error describing the problem if the table is invalid.
+    /// Either order may be `undefined` for ∞, and the table of {∞,q} is that
+    /// of {q,∞}.
+    #[wasm_bindgen(constructor)]
+    pub fn new(p: Option<usize>, q: Option<usize>, s: &str) -> Result<TilingGenerator, JsError> {
+        Ok(Self::try_new(p, q, s)?)
+    }
+
+    /// Creates the generator for the {`p`,`q`} tiling, deriving the fragment
+    /// table from the Schläfli symbol.
+    pub fn from_schlafli(p: Option<usize>, q: Option<usize>) -> Result<TilingGenerator, JsError> {
+        Ok(Self::try_from_schlafli(p, q)?)
+    }
+
+    /// Creates the generator for the uniform tiling with the given Wythoff
+    /// symbol, such as `2 3 | 7` for the truncated {7,3} tiling, with each
+    /// kind of face coloured from its own palette slot.
+    pub fn from_wythoff(symbol: &str) -> Result<TilingGenerator, JsError> {
+        Ok(Self::try_from_wythoff(symbol)?)
+    }
+
+    /// Creates the generator for the dual {q,p} tiling, positioned so that
+    /// its vertices lie at the centres of the tiles of this one.
+    pub fn dual(&self) -> Result<TilingGenerator, JsError> {
+        Ok(self.try_dual()?)
+    }
+
+    /// Returns the fragment table in the format accepted by the constructor.
+    pub fn table(&self) -> String {
+        self.data
+            .iter()
+            .map(Fragment::to_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+impl TilingGenerator {
+    pub fn try_new(p: Option<usize>, q: Option<usize>, s: &str) -> Result<Self, TilingError> {
+        check_hyperbolic(p, q)?;
+        let sides = p.or(q).unwrap();
+        let data = Fragment::parse_table(s, sides)?;
+        Ok(Self::with_data(p, q, data))
+    }
+
+    pub fn try_from_schlafli(p: Option<usize>, q: Option<usize>) -> Result<Self, TilingError> {
+        check_hyperbolic(p, q)?;
+        let data = match (p, q) {
+            (Some(p), Some(q)) => {
+                automaton::derive(p, q).ok_or(TilingError::Underivable { p, q })?
+            }
+            (sides, None) | (None, sides) => ideal::tree(sides.unwrap()),
+        };
+        Ok(Self::with_data(p, q, data))
+    }
+
+    pub fn try_from_wythoff(symbol: &str) -> Result<Self, TilingError> {
+        let symbol = wythoff::Symbol::parse(symbol)?;
+        Ok(TilingGenerator {
+            tiles: wythoff::prototiles(&symbol, 8)?,
+            ..Self::try_from_schlafli(Some(symbol.p), Some(symbol.q))?
+        })
+    }
+
+    pub fn try_dual(&self) -> Result<Self, TilingError> {
+        let p = self.sides;
+        // The vertices of {∞,p} are the centres of the tiles of {p,∞}, and the
+        // other way round, so both are drawn from the same walk.
+        let q = match self.valence {
+            None if self.apeirogonal => {
+                return Ok(TilingGenerator {
+                    origin: self.origin,
+                    ..Self::try_from_schlafli(Some(p), None)?
+                });
+            }
+            None => {
+                return Ok(TilingGenerator {
+                    origin: self.origin,
+                    ..Self::try_from_schlafli(None, Some(p))?
+                });
+            }
+            Some(q) => q,
+        };
+        let vertex = self.vertices()[0];
+        // Turn the dual tile around so that its first corner, rather than
+        // the centre of its first side, faces the centre of this tile.
+        let turn =
+            Matrix3::from_angle_z(Rad(TAU / 2.0 + TAU / (2 * p) as f64 - TAU / (2 * q) as f64));
+        Ok(TilingGenerator {
+            origin: self.origin * translation(vertex.truncate()) * turn,
+            ..Self::try_from_schlafli(Some(q), Some(p))?
+        })
+    }
+
+    fn with_data(p: Option<usize>, q: Option<usize>, data: Vec<Fragment>) -> Self {
+        let apeirogonal = p.is_none();
+        let (p, q) = if apeirogonal {
+            (q.unwrap(), p)
+        } else {
+            (p.unwrap(), q)
+        };
+        let (side, len) = dimensions(p, q);
+        let tiles = if apeirogonal {
+            ideal::apeirogon_pieces(p, 16)
+        } else {
+            vec![Prototile {
+                mesh: generate_polygon(p, side, 16),
+                paint: Paint::Fragment,
+                centre: Vector3::unit_z(),
+                corners: polygon_corners(p, side).into_iter().map(lift).collect(),
+            }]
+        };
+        TilingGenerator {
+            len,
+            sides: p,
+            valence: q,
+            apeirogonal,
+            origin: Matrix3::one(),
+            anchor: anchor::Anchor::default(),
+            tiles,
+            data,
+        }
+    }
+
+    /// Vertices of the tiling in the prototile, on the hyperboloid. These are
+    /// its corners in counterclockwise order starting with the one preceding
+    /// side 0, given as lightlike vectors if they are ideal, or its centre
+    /// for {∞,q}.
+    pub fn vertices(&self) -> Vec<Vector3<f64>> {
+        if self.apeirogonal {
+            return vec![Vector3::unit_z()];
+        }
+        let (side, _) = dimensions(self.sides, self.valence);
+        polygon_corners(self.sides, side)
+            .into_iter()
+            .map(lift)
+            .collect()
+    }
+
+    /// Returns the transforms of the tiles up to `depth` steps away from the
+    /// origin, each taking the centre of the prototile to that of the tile.
+    pub fn transforms(&self, depth: usize) -> Vec<Matrix3<f64>> {
+        let mut transforms = Vec::new();
+        self.walk(depth, |_, tr| transforms.push(tr));
+        transforms
+    }
+
+    /// Generates bands of the given colour along the edges of the tiles up
+    /// to `depth`, for drawing the tiling as an outline.
+    pub fn generate_edges(&self, color: Color, depth: usize) -> InstancedMesh {
+        let (side, _) = dimensions(self.sides, self.valence);
+        let corners = polygon_corners(self.sides, side);
+        let centre = Vector2::new(0.0, 0.0);
+        let mut outline = Mesh::new();
+        for (i, &from) in corners.iter().enumerate() {
+            let to = corners[(i + 1) % corners.len()];
+            outline.extend(if self.apeirogonal {
+                // The sides of the apeirogons join the centres of neighbouring
+                // walked tiles.
+                let midpoint = (from + to) / 2.0;
+                generate_band(centre, midpoint, to, (-EDGE_WIDTH, EDGE_WIDTH), 16)
+            } else {
+                generate_band(from, to, centre, (0.0, EDGE_WIDTH), 16)
+            });
+        }
+        let mut instances = Instances::new(
+            vec![(&outline, Vector3::unit_z())],
+            std::slice::from_ref(&color),
+        );
+        self.walk_addressed(depth, |address, _, origin| {
+            instances.push(0, origin, 0, address)
+        });
+        instances.finish()
+    }
+
+    /// Calls `push` with the fragment id and transform of every tile up to
+    /// `depth` steps away from the origin.
+    pub fn walk<F: FnMut(u16, Matrix3<f64>)>(&self, depth: usize, mut push: F) {
+        self.walk_addressed(depth, |_, id, tr| push(id, tr));
+    }
+
+    /// Like [`TilingGenerator::walk`], but also passes the address of every
+    /// tile in the fragment tree: the sides crossed on the way to it from the
+    /// tile around the origin, numbered from the side it was entered by.
+    pub fn walk_addressed<F: FnMut(&[u16], u16, Matrix3<f64>)>(&self, depth: usize, push: F) {
+        let mut state = State {
+            sides: self.sides,
+            rotation_matrix: rotation(self.sides),
+            forward_transform: forward(self.len),
+            data: &self.data,
+            address: Vec::new(),
+            push,
+        };
+        layer(&mut state, self.origin, 0, depth);
+    }
+}
+impl TilingGenerator {
+    /// Whether the tiles are the polygons of the Schläfli symbol, each painted
+    /// in one colour by its fragment, rather than drawn in pieces.
+    fn is_plain(&self) -> bool {
+        !self.apeirogonal
+            && matches!(
+                self.tiles[..],
+                [Prototile {
+                    paint: Paint::Fragment,
+                    ..
+                }]
+            )
+    }
+}
+impl Tiling for TilingGenerator {
+    fn domain(&self) -> Option<Domain> {
+        // Tilings drawn in pieces are not painted by tile.
+        if !self.is_plain() {
+            return None;
+        }
+        let (side, _) = dimensions(self.sides, self.valence);
+        let corners = polygon_corners(self.sides, side);
+        let sides = (0..self.sides)
+            .map(|i| {
+                let from = corners[(i + self.sides - 1) % self.sides].extend(1.0);
+                let c = from.cross(corners[i].extend(1.0));
+                let n = Vector3::new(c.x, c.y, -c.z) / minkowski(c, c).sqrt();
+                // The centre is at the origin, on the inside.
+                self.origin * if n.z > 0.0 { -n } else { n }
+            })
+            .collect();
+        Some(Domain {
+            sides,
+            centre: self.origin * Vector3::unit_z(),
+            paint: DomainPaint::Steps,
+        })
+    }
+
+    fn neighbours(&self) -> Neighbours {
+        // The walk carries the pieces of the tile around the origin, as well
+        // as the tile itself, onto each neighbour.
+        let inverse = lorentz_inverse(self.origin);
+        Neighbours {
+            centre: self.origin * Vector3::unit_z(),
+            symmetries: self.transforms(1)[1..]
+                .iter()
+                .map(|&tr| tr * inverse)
+                .collect(),
+        }
+    }
+
+    fn generate(&self, colors: &[Color], depth: usize) -> InstancedMesh {
+        let tiles = self.tiles.iter().map(|t| (&t.mesh, t.centre)).collect();
+        let mut instances = Instances::new(tiles, colors);
+        let mut push = |address: &[u16], id: u16, corners: &[usize], origin| {
+            for (k, tile) in self.tiles.iter().enumerate() {
+                let slot = match tile.paint {
+                    Paint::Fragment => id as usize,
+                    Paint::Slot(slot) => slot,
+                    Paint::Corner(corner) => corners[corner],
+                };
+                instances.push(k, origin, slot, address);
+            }
+        };
+        if self.apeirogonal {
+            self.walk_corners(depth, colors.len().min(PALETTE_SIZE), push);
+        } else {
+            self.walk_addressed(depth, |address, id, origin| push(address, id, &[], origin));
+        }
+        let mut mesh = instances.finish();
+        self.paint_anchored(&mut mesh);
+        mesh
+    }
+
+    fn prototile_corners(&self) -> Vec<Vec<Vector3<f64>>> {
+        self.tiles.iter().map(|tile| tile.corners.clone()).collect()
+    }
+
+    fn recentre(&mut self, mesh: &mut InstancedMesh, symmetry: Matrix3<f64>) -> bool {
+        if !self.walk_coloured() {
+            return false;
+        }
+        if !self.move_anchor(symmetry, mesh.palette.len()) {
+            warn!("lost track of the tile under the camera, colouring the tiles from it afresh");
+        }
+        self.paint_anchored(mesh);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(table: &str) -> ParseError {
+        match Fragment::parse_table(table, 4) {
+            Ok(_) => panic!("{:?} was accepted", table),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn parses_table() {
+        let table = "0,0,0,0\n\n - , 1,-,- \n";
+        let data = Fragment::parse_table(table, 4).unwrap();
+        let rows: Vec<_> = data.iter().map(Fragment::to_string).collect();
+        assert_eq!(rows, ["0,0,0,0", "-,1,-,-"]);
+    }
+
+    #[test]
+    fn bad_token() {
+        let err = error("0,0,0,0\n-,1, x,-");
+        assert_eq!((err.line, err.column), (2, 6));
+        assert_eq!(err.kind, ParseErrorKind::BadToken("x".to_owned()));
+        assert_eq!(
+            error("-,-1,-,-").kind,
+            ParseErrorKind::BadToken("-1".to_owned())
+        );
+        assert_eq!(error("-,,-,-").column, 3);
+    }
+
+    #[test]
+    fn wrong_arity() {
+        let err = error("0,0,0,0\n\n-,-,-,-, 0,-");
+        assert_eq!((err.line, err.column), (3, 10));
+        let expected = ParseErrorKind::WrongArity {
+            expected: 4,
+            found: 6,
+        };
+        assert_eq!(err.kind, expected);
+
+        let err = error("0,0,0,0\n-, -  ");
+        assert_eq!((err.line, err.column), (2, 5));
+        let expected = ParseErrorKind::WrongArity {
+            expected: 4,
+            found: 2,
+        };
+        assert_eq!(err.kind, expected);
+    }
+
+    #[test]
+    fn dangling_reference() {
+        let err = error("0,0,0,0\n-,1,  2,-");
+        assert_eq!((err.line, err.column), (2, 7));
+        assert_eq!(
+            err.kind,
+            ParseErrorKind::DanglingReference { id: 2, rows: 2 }
+        );
+    }
+
+    #[test]
+    fn empty() {
+        for table in ["", "\n  \n"] {
+            let err = error(table);
+            assert_eq!((err.line, err.column), (1, 1));
+            assert_eq!(err.kind, ParseErrorKind::Empty);
+        }
+    }
+
+    /// Whether the points `u` and `v` of the hyperboloid are the same.
+    fn same_point(u: Vector3<f64>, v: Vector3<f64>) -> bool {
+        -minkowski(u, v) < 1.0 + 1e-9
+    }
+
+    #[test]
+    fn dual_vertices_are_centres() {
+        for (p, q) in [(4, 5), (7, 3), (3, 8), (5, 5)] {
+            let tiling = TilingGenerator::try_from_schlafli(Some(p), Some(q)).unwrap();
+            let dual = tiling.try_dual().unwrap();
+            assert_eq!(dual.vertices().len(), q);
+            // The tiles of the dual reach further than the first layers of
+            // small tiles, so the tiling is walked much deeper.
+            let transforms = tiling.transforms(8);
+            let centres: Vec<_> = transforms
+                .iter()
+                .map(|&tr| tr * Vector3::unit_z())
+                .collect();
+            let vertices: Vec<_> = transforms
+                .iter()
+                .flat_map(|&tr| tiling.vertices().into_iter().map(move |v| tr * v))
+                .collect();
+            for tr in dual.transforms(1) {
+                for v in dual.vertices() {
+                    let v = tr * v;
+                    assert!(
+                        centres.iter().any(|&c| same_point(c, v)),
+                        "{{{},{}}}: {:?} is not a centre",
+                        p,
+                        q,
+                        v
+                    );
+                }
+                // The other way round, the tiles of the dual are centred on
+                // the vertices of the tiling.
+                let c = tr * Vector3::unit_z();
+                assert!(vertices.iter().any(|&v| same_point(v, c)));
+            }
+        }
+    }
+
+    #[test]
+    fn dual_counts() {
+        for (p, q) in [
+            (Some(4), Some(5)),
+            (Some(7), Some(3)),
+            (Some(3), Some(8)),
+            (Some(5), None),
+            (None, Some(4)),
+        ] {
+            let dual = TilingGenerator::try_from_schlafli(p, q)
+                .unwrap()
+                .try_dual()
+                .unwrap();
+            let swapped = TilingGenerator::try_from_schlafli(q, p).unwrap();
+            for depth in 0..5 {
+                assert_eq!(
+                    dual.transforms(depth).len(),
+                    swapped.transforms(depth).len(),
+                    "{{{},{}}} to depth {}",
+                    Order(p),
+                    Order(q),
+                    depth
+                );
+            }
+        }
+    }
+}
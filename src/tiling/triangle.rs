@@ -0,0 +1,242 @@
+//! Tilings by the fundamental triangles of hyperbolic triangle groups.
+use std::f64::consts::PI;
+
+use cgmath::{Matrix3, One, Vector2, Vector3};
+use wasm_bindgen::prelude::*;
+
+use super::{
+    generate_fan, minkowski, Domain, DomainPaint, Instances, Mesh, Neighbours, Tiling, TilingError,
+};
+use crate::{Color, InstancedMesh};
+
+/// Distance covered by one layer of depth, comparable to the distance between
+/// neighbouring tiles of a polygon tiling.
+const LAYER_WIDTH: f64 = 1.0;
+
+/// Reflection across the geodesic with unit spacelike normal `n`.
+fn reflection(n: Vector3<f64>) -> Matrix3<f64> {
+    let jn = Vector3::new(n.x, n.y, -n.z);
+    Matrix3::one() - Matrix3::from_cols(n * jn.x, n * jn.y, n * jn.z) * 2.0
+}
+
+/// Returns the corners of the triangle with angles π/p, π/q and π/r, in that
+/// order, with the first one at the origin and the second one on the x axis.
+pub(super) fn corners(p: usize, q: usize, r: usize) -> Result<[Vector3<f64>; 3], TilingError> {
+    if p < 2 || q < 2 || r < 2 || p * q + q * r + r * p >= p * q * r {
+        return Err(TilingError::NotHyperbolicTriangle { p, q, r });
+    }
+    let (alpha, beta, gamma) = (PI / p as f64, PI / q as f64, PI / r as f64);
+    let cosh_b = (beta.cos() + alpha.cos() * gamma.cos()) / (alpha.sin() * gamma.sin());
+    let cosh_c = (gamma.cos() + alpha.cos() * beta.cos()) / (alpha.sin() * beta.sin());
+    let sinh_b = (cosh_b * cosh_b - 1.0).sqrt();
+    let sinh_c = (cosh_c * cosh_c - 1.0).sqrt();
+    Ok([
+        Vector3::unit_z(),
+        Vector3::new(sinh_c, 0.0, cosh_c),
+        Vector3::new(sinh_b * alpha.cos(), sinh_b * alpha.sin(), cosh_b),
+    ])
+}
+
+/// Hyperbolic triangle together with the reflections across its sides.
+pub(super) struct Triangle {
+    pub corners: [Vector3<f64>; 3],
+    /// Inward-pointing normals of the sides, each opposite the corner with
+    /// the same index.
+    pub normals: [Vector3<f64>; 3],
+    pub mirrors: [Matrix3<f64>; 3],
+    pub centre: Vector3<f64>,
+}
+impl Triangle {
+    pub fn new(corners: [Vector3<f64>; 3]) -> Self {
+        let sum = corners[0] + corners[1] + corners[2];
+        let centre = sum / (-minkowski(sum, sum)).sqrt();
+
+        let normals = [(1, 2), (2, 0), (0, 1)].map(|(i, j)| {
+            let c = corners[i].cross(corners[j]);
+            let n = Vector3::new(c.x, c.y, -c.z);
+            let n = n / minkowski(n, n).sqrt();
+            if minkowski(n, centre) < 0.0 {
+                -n
+            } else {
+                n
+            }
+        });
+        let mirrors = normals.map(reflection);
+
+        Triangle {
+            corners,
+            normals,
+            mirrors,
+            centre,
+        }
+    }
+}
+
+/// Tiling by the triangle with angles π/p, π/q and π/r and its mirror
+/// images, coloured by the parity of the number of reflections.
+#[wasm_bindgen]
+pub struct TriangleTiling {
+    triangle: Triangle,
+    tile: Mesh<Vector3<f64>>,
+}
+#[wasm_bindgen]
+impl TriangleTiling {
+    #[wasm_bindgen(constructor)]
+    pub fn new(p: usize, q: usize, r: usize) -> Result<TriangleTiling, JsError> {
+        Ok(Self::try_new(p, q, r)?)
+    }
+}
+impl TriangleTiling {
+    pub fn try_new(p: usize, q: usize, r: usize) -> Result<Self, TilingError> {
+        // The corner with angle π/p sits at the origin, so that its star is
+        // centred on screen.
+        let triangle = Triangle::new(corners(p, q, r)?);
+
+        let klein = triangle.corners.map(|v| Vector2::new(v.x / v.z, v.y / v.z));
+        let tile = generate_fan((klein[0] + klein[1] + klein[2]) / 3.0, &klein, 8);
+
+        Ok(TriangleTiling { triangle, tile })
+    }
+
+    /// Calls `push` with the address, parity and transform of every triangle
+    /// whose centre lies within `radius` of the centre of the fundamental
+    /// one. The address lists the mirrors reflected across on the way to it.
+    ///
+    /// Each group element is visited once, from the element obtained by
+    /// dropping its smallest right descent, which is found by checking on
+    /// which side of the mirrors the fundamental triangle lies. That element
+    /// is the mirror image across a side facing the fundamental triangle, so
+    /// it is closer and the walk can stop at the radius.
+    pub fn walk<F: FnMut(&[u16], bool, Matrix3<f64>)>(&self, radius: f64, mut push: F) {
+        let t = &self.triangle;
+        let ascends = |tr: Matrix3<f64>, s: usize| minkowski(tr * t.normals[s], t.centre) > 0.0;
+        let mut stack = vec![(Matrix3::one(), false, Vec::new())];
+        while let Some((tr, parity, address)) = stack.pop() {
+            push(&address, parity, tr);
+            for s in 0..3 {
+                if !ascends(tr, s) {
+                    continue;
+                }
+                let next = tr * t.mirrors[s];
+                if -minkowski(next * t.centre, t.centre) <= radius.cosh()
+                    && (0..s).all(|side| ascends(next, side))
+                {
+                    let mut address = address.clone();
+                    address.push(s as u16);
+                    stack.push((next, !parity, address));
+                }
+            }
+        }
+    }
+}
+impl Tiling for TriangleTiling {
+    fn generate(&self, colors: &[Color], depth: usize) -> InstancedMesh {
+        let mut instances = Instances::new(vec![(&self.tile, self.triangle.centre)], colors);
+        self.walk(depth as f64 * LAYER_WIDTH, |address, parity, origin| {
+            instances.push(0, origin, parity as usize, address);
+        });
+        instances.finish()
+    }
+
+    fn domain(&self) -> Option<Domain> {
+        Some(Domain {
+            sides: self.triangle.normals.to_vec(),
+            centre: self.triangle.centre,
+            paint: DomainPaint::Parity,
+        })
+    }
+
+    fn neighbours(&self) -> Neighbours {
+        // Only even elements keep the colours. The triangles sharing a corner
+        // with the fundamental one are within twice its circumradius.
+        let t = &self.triangle;
+        let circumradius = t
+            .corners
+            .iter()
+            .map(|&v| (-minkowski(v, t.centre)).acosh())
+            .fold(0.0, f64::max);
+        let mut symmetries = Vec::new();
+        self.walk(2.0 * circumradius, |_, parity, tr| {
+            if !parity && tr != Matrix3::one() {
+                symmetries.push(tr);
+            }
+        });
+        Neighbours {
+            centre: t.centre,
+            symmetries,
+        }
+    }
+
+    fn prototile_corners(&self) -> Vec<Vec<Vector3<f64>>> {
+        vec![self.triangle.corners.to_vec()]
+    }
+
+    fn recentre(&mut self, _: &mut InstancedMesh, _: Matrix3<f64>) -> bool {
+        // The neighbours are all even, so the colours are kept as they are.
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    /// Centre of a triangle rounded, for comparing triangles reached in
+    /// different ways.
+    fn key(v: Vector3<f64>) -> (i64, i64) {
+        let p = v.truncate() / (1.0 + v.z);
+        ((p.x * 1e8).round() as i64, (p.y * 1e8).round() as i64)
+    }
+
+    #[test]
+    fn mirrors_close_up() {
+        for (p, q, r) in [(2, 3, 7), (2, 4, 5), (3, 3, 4)] {
+            let t = Triangle::new(corners(p, q, r).unwrap());
+            // The sides meeting at a corner are the two not opposite it.
+            for (corner, n) in [(0, p), (1, q), (2, r)] {
+                let (i, j) = ((corner + 1) % 3, (corner + 2) % 3);
+                let turn = t.mirrors[i] * t.mirrors[j];
+                let mut power = Matrix3::one();
+                for k in 1..=n {
+                    power = power * turn;
+                    let error: [[f64; 3]; 3] = (power - Matrix3::one()).into();
+                    let closed = error.iter().flatten().all(|e| e.abs() < 1e-9);
+                    assert_eq!(closed, k == n, "({},{},{}) corner {}", p, q, r, corner);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn walk_visits_each_triangle_once() {
+        for (p, q, r) in [(2, 3, 7), (2, 4, 5)] {
+            let tiling = TriangleTiling::try_new(p, q, r).unwrap();
+            let t = &tiling.triangle;
+            for depth in 1..=4 {
+                let radius = depth as f64 * LAYER_WIDTH;
+                let mut walked = Vec::new();
+                tiling.walk(radius, |_, _, tr| walked.push(key(tr * t.centre)));
+                let unique: HashSet<_> = walked.iter().copied().collect();
+                assert_eq!(unique.len(), walked.len(), "({},{},{})", p, q, r);
+
+                // Reflect across every side until no new triangle is found.
+                let mut found = HashSet::from([key(t.centre)]);
+                let mut stack = vec![Matrix3::one()];
+                while let Some(tr) = stack.pop() {
+                    for mirror in t.mirrors {
+                        let next = tr * mirror;
+                        let centre = next * t.centre;
+                        if -minkowski(centre, t.centre) <= radius.cosh()
+                            && found.insert(key(centre))
+                        {
+                            stack.push(next);
+                        }
+                    }
+                }
+                assert_eq!(unique, found, "({},{},{}) to depth {}", p, q, r, depth);
+            }
+        }
+    }
+}
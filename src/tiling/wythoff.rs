@@ -0,0 +1,408 @@
+//! Uniform tilings obtained by Wythoff's construction.
+//!
+//! The generating point lies in the triangle with angles π/p, π/q and π/2
+//! whose corners are the centre of a tile of {p,q}, one of its vertices and
+//! the midpoint of one of its sides. Every face of the uniform tiling is
+//! centred on the image of such a corner, except for the triangles added by
+//! the snub, which lie in the odd images of the triangle. The faces are
+//! clipped to the tile, so that drawing the pieces with every tile of {p,q}
+//! draws the uniform tiling.
+use std::f64::consts::PI;
+
+use cgmath::{InnerSpace, Matrix, Matrix3, One, Rad, SquareMatrix, Vector2, Vector3};
+
+use super::triangle::{corners, Triangle};
+use super::{
+    check_hyperbolic, dimensions, generate_fan, kleinpoint, minkowski, polygon_corners, Paint,
+    Prototile, TilingError,
+};
+
+/// Distance under which points in Klein coordinates are considered equal.
+const EPSILON: f64 = 1e-9;
+
+/// Upper bound on the iterations spent placing the generating point of a
+/// snub.
+const SNUB_ITERATIONS: usize = 1000;
+
+/// Palette slot of the triangles added by the snub, following those of the
+/// faces centred on the three corners. These triangles aren't centred on a
+/// corner, but they are all alike, so every piece of every one of them takes
+/// this slot.
+const SNUB_SLOT: usize = 3;
+
+/// Where the generating point lies in the triangle.
+enum Generator {
+    /// Equidistant from the mirrors opposite the marked corners, and on the
+    /// other ones.
+    Mirrors([bool; 3]),
+    /// Equidistant from its images under the rotations about the corners.
+    Snub,
+}
+
+/// Wythoff symbol over the triangle with angles π/p, π/q and π/2.
+pub(super) struct Symbol {
+    pub p: usize,
+    pub q: usize,
+    generator: Generator,
+}
+impl Symbol {
+    /// Parses a symbol such as `2 q | p`, `p q 2 |` or `| p q 2`. The
+    /// numbers before the bar are the corners whose opposite mirror moves
+    /// the generating point, and nothing before it stands for the snub.
+    pub fn parse(s: &str) -> Result<Self, TilingError> {
+        let err = || TilingError::BadWythoff(s.trim().to_owned());
+        let (before, after) = s.split_once('|').ok_or_else(err)?;
+        let numbers = before
+            .split_whitespace()
+            .chain(after.split_whitespace())
+            .map(str::parse::<usize>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| err())?;
+        if numbers.len() != 3 {
+            return Err(err());
+        }
+
+        // Corner 2 is the right angle, the others are taken in order.
+        let r = numbers.iter().position(|&n| n == 2).ok_or_else(err)?;
+        let mut corner = [2; 3];
+        let (i, j) = match r {
+            0 => (1, 2),
+            1 => (0, 2),
+            _ => (0, 1),
+        };
+        corner[i] = 0;
+        corner[j] = 1;
+        let (p, q) = (numbers[i], numbers[j]);
+        check_hyperbolic(Some(p), Some(q))?;
+
+        let generator = match before.split_whitespace().count() {
+            0 => Generator::Snub,
+            n => {
+                let mut active = [false; 3];
+                for &c in &corner[..n] {
+                    active[c] = true;
+                }
+                Generator::Mirrors(active)
+            }
+        };
+        Ok(Symbol { p, q, generator })
+    }
+}
+
+fn klein(v: Vector3<f64>) -> Vector2<f64> {
+    Vector2::new(v.x / v.z, v.y / v.z)
+}
+
+/// Point at distances from the sides whose hyperbolic sines are proportional
+/// to `weights`.
+fn weighted_point(triangle: &Triangle, weights: [f64; 3]) -> Vector3<f64> {
+    let [a, b, c] = triangle.normals.map(|n| Vector3::new(n.x, n.y, -n.z));
+    let w = Matrix3::from_cols(a, b, c)
+        .transpose()
+        .invert()
+        .expect("the sides of a triangle are independent")
+        * Vector3::from(weights);
+    w / (-minkowski(w, w)).sqrt()
+}
+
+/// Finds the point whose images under the rotations by 2π/`orders[i]` about
+/// the corners are all at the same distance from it.
+fn snub_point(triangle: &Triangle, orders: [usize; 3]) -> Vector3<f64> {
+    let mut weights = [1.0; 3];
+    let mut v = weighted_point(triangle, weights);
+    for _ in 0..SNUB_ITERATIONS {
+        // Hyperbolic sine of half the distance to each image.
+        let chords = [0, 1, 2].map(|i| {
+            let cosh = -minkowski(v, triangle.corners[i]);
+            (cosh * cosh - 1.0).max(0.0).sqrt() * (PI / orders[i] as f64).sin()
+        });
+        let mean = chords.iter().sum::<f64>() / 3.0;
+        if chords.iter().all(|c| (c / mean - 1.0).abs() < EPSILON) {
+            break;
+        }
+        // Moving away from a side moves towards the opposite corner.
+        for (w, c) in weights.iter_mut().zip(chords) {
+            *w *= (c / mean).sqrt();
+        }
+        v = weighted_point(triangle, weights);
+    }
+    v
+}
+
+/// Images of the triangle around its corner `corner`, with the parity of
+/// the number of reflections.
+fn star(triangle: &Triangle, orders: [usize; 3], corner: usize) -> Vec<(Matrix3<f64>, bool)> {
+    let a = triangle.mirrors[(corner + 1) % 3];
+    let b = triangle.mirrors[(corner + 2) % 3];
+    let mut tr = Matrix3::one();
+    let mut star = Vec::with_capacity(2 * orders[corner]);
+    for _ in 0..orders[corner] {
+        star.push((tr, false));
+        star.push((tr * a, true));
+        tr = tr * a * b;
+    }
+    star
+}
+
+/// Face of the uniform tiling in Klein coordinates, in counterclockwise
+/// order.
+struct Face {
+    slot: usize,
+    centre: Vector2<f64>,
+    corners: Vec<Vector2<f64>>,
+}
+impl Face {
+    /// Builds the face with the given corners, which may contain duplicates,
+    /// unless it degenerates into an edge or a vertex.
+    fn new(slot: usize, points: impl Iterator<Item = Vector2<f64>>) -> Option<Self> {
+        let mut corners: Vec<Vector2<f64>> = Vec::new();
+        for v in points {
+            if corners.iter().all(|&c| (c - v).magnitude() > EPSILON) {
+                corners.push(v);
+            }
+        }
+        if corners.len() < 3 {
+            return None;
+        }
+        let centre = corners.iter().sum::<Vector2<f64>>() / corners.len() as f64;
+        let angle = |v: &Vector2<f64>| (v.y - centre.y).atan2(v.x - centre.x);
+        corners.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+        Some(Face {
+            slot,
+            centre,
+            corners,
+        })
+    }
+}
+
+/// Clips a convex polygon to a convex window, both in counterclockwise
+/// order.
+fn clip(polygon: &[Vector2<f64>], window: &[Vector2<f64>]) -> Vec<Vector2<f64>> {
+    let mut out = polygon.to_vec();
+    for (i, &a) in window.iter().enumerate() {
+        let b = window[(i + 1) % window.len()];
+        let side = |v: Vector2<f64>| (b - a).perp_dot(v - a);
+        let input = std::mem::take(&mut out);
+        for (j, &u) in input.iter().enumerate() {
+            let w = input[(j + 1) % input.len()];
+            let (su, sw) = (side(u), side(w));
+            if su >= 0.0 {
+                out.push(u);
+            }
+            if (su >= 0.0) != (sw >= 0.0) {
+                out.push(u + (w - u) * (su / (su - sw)));
+            }
+        }
+        if out.is_empty() {
+            break;
+        }
+    }
+    out.dedup_by(|a, b| (*a - *b).magnitude() < EPSILON);
+    while out.len() > 1 && (out[0] - out[out.len() - 1]).magnitude() < EPSILON {
+        out.pop();
+    }
+    out
+}
+
+fn area(polygon: &[Vector2<f64>]) -> f64 {
+    (0..polygon.len())
+        .map(|i| polygon[i].perp_dot(polygon[(i + 1) % polygon.len()]))
+        .sum::<f64>()
+        / 2.0
+}
+
+/// Builds the pieces of the faces of the uniform tiling that lie within the
+/// prototile of {p,q}, each coloured from the slot of the corner its face is
+/// centred on.
+pub(super) fn prototiles(symbol: &Symbol, subdiv: usize) -> Result<Vec<Prototile>, TilingError> {
+    let (p, q) = (symbol.p, symbol.q);
+    let orders = [p, q, 2];
+
+    // Put the second corner on a vertex of the prototile, and the third one
+    // on the midpoint of the following side.
+    let turn = Matrix3::from_angle_z(Rad(PI + PI / p as f64));
+    let triangle = Triangle::new(corners(p, q, 2)?.map(|c| turn * c));
+    let window = polygon_corners(p, dimensions(p, Some(q)).0);
+
+    let snub = matches!(symbol.generator, Generator::Snub);
+    let v = match symbol.generator {
+        Generator::Mirrors(active) => {
+            weighted_point(&triangle, active.map(|a| if a { 1.0 } else { 0.0 }))
+        }
+        Generator::Snub => snub_point(&triangle, orders),
+    };
+
+    // Every face meeting the prototile is centred on a corner of one of the
+    // images of the triangle within it, or lies in an image next to them.
+    let stars = [0, 1, 2].map(|corner| star(&triangle, orders, corner));
+    let mut faces: Vec<Face> = Vec::new();
+    let mut add = |face: Option<Face>| {
+        if let Some(face) = face {
+            if faces
+                .iter()
+                .all(|f| (f.centre - face.centre).magnitude() > EPSILON)
+            {
+                faces.push(face);
+            }
+        }
+    };
+    for &(g, g_odd) in &stars[0] {
+        for (corner, star) in stars.iter().enumerate() {
+            let images = star
+                .iter()
+                .filter(|&&(_, h_odd)| !snub || g_odd == h_odd)
+                .map(|&(h, _)| klein(g * h * v));
+            add(Face::new(corner, images));
+
+            if snub {
+                for &(h, h_odd) in star {
+                    if g_odd != h_odd {
+                        let images = triangle.mirrors.iter().map(|&m| klein(g * h * m * v));
+                        add(Face::new(SNUB_SLOT, images));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(faces
+        .iter()
+        .filter_map(|face| {
+            let piece = clip(&face.corners, &window);
+            if piece.len() < 3 || area(&piece) < EPSILON {
+                return None;
+            }
+            let centre = piece.iter().sum::<Vector2<f64>>() / piece.len() as f64;
+            // Unlike the mean in Klein coordinates, the mean on the
+            // hyperboloid moves along with the face.
+            let mean: Vector3<f64> = face.corners.iter().map(|&c| kleinpoint(c)).sum();
+            Some(Prototile {
+                mesh: generate_fan(centre, &piece, subdiv),
+                paint: Paint::Slot(face.slot),
+                centre: mean / (-minkowski(mean, mean)).sqrt(),
+                corners: piece.iter().map(|&v| kleinpoint(v)).collect(),
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::tiling::{Tiling, TilingGenerator};
+    use crate::{in_triangle, lorentz_inverse};
+
+    /// Triangles of the mesh of `tile` in Klein coordinates.
+    fn triangles(tile: &Prototile) -> Vec<[Vector2<f64>; 3]> {
+        let mesh = &tile.mesh;
+        mesh.index
+            .chunks_exact(3)
+            .map(|t| [0, 1, 2].map(|k| klein(mesh.vertex[t[k] as usize])))
+            .collect()
+    }
+
+    fn pieces(symbol: &str) -> Vec<Prototile> {
+        prototiles(&Symbol::parse(symbol).unwrap(), 4).unwrap()
+    }
+
+    #[test]
+    fn pieces_tile_the_prototile() {
+        for symbol in [
+            "2 3 | 7", "7 3 | 2", "| 7 3 2", "3 | 7 2", "7 3 2 |", "2 4 | 5",
+        ] {
+            let s = Symbol::parse(symbol).unwrap();
+            let window = polygon_corners(s.p, dimensions(s.p, Some(s.q)).0);
+            let pieces: Vec<_> = pieces(symbol).iter().map(triangles).collect();
+            let total: f64 = pieces.iter().flatten().map(|t| area(t).abs()).sum();
+            assert!((total - area(&window)).abs() < 1e-9, "{}", symbol);
+
+            // Points of the prototile lie in exactly one piece, sampled off
+            // any grid the pieces could line up with.
+            let radius = window[0].magnitude();
+            for i in 0..40 {
+                for j in 0..40 {
+                    let p = Vector2::new(i as f64 + 0.5, j as f64 + 0.5f64.sqrt()) / 20.0
+                        - Vector2::new(1.0, 1.0);
+                    let p = p * radius;
+                    let inside = window.iter().enumerate().all(|(k, &a)| {
+                        let b = window[(k + 1) % window.len()];
+                        (b - a).perp_dot(p - a) > 1e-9
+                    });
+                    if !inside {
+                        continue;
+                    }
+                    let count = pieces
+                        .iter()
+                        .filter(|piece| piece.iter().any(|t| in_triangle(t, p)))
+                        .count();
+                    assert_eq!(count, 1, "{} at {:?}", symbol, p);
+                }
+            }
+        }
+    }
+
+    fn slots(symbol: &str) -> BTreeSet<usize> {
+        pieces(symbol)
+            .iter()
+            .map(|tile| match tile.paint {
+                Paint::Slot(slot) => slot,
+                paint => panic!("{:?} is not a face type", paint),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn face_type_slots() {
+        // Heptagons, triangles and the squares between them.
+        assert_eq!(slots("7 3 | 2"), BTreeSet::from([0, 1, 2]));
+        // Heptagons, triangles around the vertices, and the snub triangles.
+        assert_eq!(slots("| 7 3 2"), BTreeSet::from([0, 1, 3]));
+        // Truncated heptagons and triangles.
+        assert_eq!(slots("2 3 | 7"), BTreeSet::from([0, 1]));
+    }
+
+    #[test]
+    fn pieces_of_a_face_share_it() {
+        for symbol in ["2 3 | 7", "7 3 | 2", "| 7 3 2", "3 | 7 2", "7 3 2 |"] {
+            let tiling = TilingGenerator::try_from_wythoff(symbol).unwrap();
+            let mesh = tiling.generate(crate::COLORS, 3);
+            let transforms = tiling.transforms(3);
+            for (tile, part) in tiling.tiles.iter().zip(&mesh.parts) {
+                let root = part.instances.start as usize;
+                let face = mesh.placed[root].face;
+                let shared: Vec<_> = (0..mesh.placed.len())
+                    .filter(|&i| mesh.placed[i].face == face)
+                    .collect();
+                for &i in &shared {
+                    assert_eq!(mesh.instances[i].color, mesh.instances[root].color);
+                }
+                // Every image of the triangle has its side from a vertex to
+                // the middle of a side on the side of the prototile, and the
+                // snub triangle in an odd image reaches across its three
+                // sides, so it is cut in two by that side, with its centre
+                // in one of the tiles.
+                if matches!(tile.paint, Paint::Slot(SNUB_SLOT)) {
+                    assert_eq!(shared.len(), 2, "{}", symbol);
+                    continue;
+                }
+                // The other faces are centred on the centre, a corner or the
+                // middle of a side of the tiles they have pieces in.
+                let window =
+                    polygon_corners(tiling.sides, dimensions(tiling.sides, tiling.valence).0);
+                let tiles = transforms
+                    .iter()
+                    .filter(|&&tr| {
+                        let c = klein(lorentz_inverse(tr) * tile.centre);
+                        window.iter().enumerate().all(|(k, &a)| {
+                            let b = window[(k + 1) % window.len()];
+                            (b - a).perp_dot(c - a) > -1e-9
+                        })
+                    })
+                    .count();
+                assert_eq!(shared.len(), tiles, "{}", symbol);
+            }
+        }
+    }
+}
@@ -1,22 +1,28 @@
 use cgmath::Vector2;
-use js_sys::Function;
-use raw_window_handle::{
-    HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle, WebDisplayHandle,
-    WebWindowHandle,
+use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
+#[cfg(target_arch = "wasm32")]
+use {
+    js_sys::Function,
+    raw_window_handle::{RawDisplayHandle, RawWindowHandle, WebDisplayHandle, WebWindowHandle},
+    wasm_bindgen::prelude::*,
+    web_sys::HtmlCanvasElement,
 };
-use wasm_bindgen::prelude::*;
-use web_sys::HtmlCanvasElement;
 
 pub trait Window: HasRawWindowHandle + HasRawDisplayHandle {
     fn size(&self) -> Vector2<u32>;
     fn request_redraw(&self);
+    /// Time in milliseconds since an arbitrary point, for timing animations.
+    fn now(&self) -> f64;
 }
 
+/// Canvas of the page the app draws into, only found in the browser.
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub struct AppWindow {
     redraw: Function,
     canvas: HtmlCanvasElement,
 }
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 impl AppWindow {
     #[wasm_bindgen(constructor)]
@@ -26,6 +32,7 @@ impl AppWindow {
     }
 }
 
+#[cfg(target_arch = "wasm32")]
 unsafe impl HasRawWindowHandle for AppWindow {
     fn raw_window_handle(&self) -> RawWindowHandle {
         let mut handle = WebWindowHandle::empty();
@@ -39,11 +46,13 @@ unsafe impl HasRawWindowHandle for AppWindow {
         RawWindowHandle::Web(handle)
     }
 }
+#[cfg(target_arch = "wasm32")]
 unsafe impl HasRawDisplayHandle for AppWindow {
     fn raw_display_handle(&self) -> RawDisplayHandle {
         RawDisplayHandle::Web(WebDisplayHandle::empty())
     }
 }
+#[cfg(target_arch = "wasm32")]
 impl Window for AppWindow {
     fn size(&self) -> Vector2<u32> {
         Vector2::new(self.canvas.width(), self.canvas.height())
@@ -52,4 +61,8 @@ impl Window for AppWindow {
     fn request_redraw(&self) {
         self.redraw.call0(&JsValue::NULL).unwrap();
     }
+
+    fn now(&self) -> f64 {
+        js_sys::Date::now()
+    }
 }
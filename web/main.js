@@ -1,4 +1,4 @@
-import init, { App, AppWindow, TilingGenerator as Tiling } from './hyperbolic.js';
+import init, { App, AppWindow, TilingGenerator as Tiling, TriangleTiling } from './hyperbolic.js';
 
 let app, tilingGenerator;
 let container = document.getElementById('outer-view');
@@ -13,36 +13,225 @@ addEventListener('resize', e => {
   view.height = height;
   app.resize(width, height);
 });
+// Pointers down on the view, so that two fingers twisting turn it and
+// pinching zooms it.
+let pointers = new Map();
+// Where the last press started, so that a press that hardly moved selects
+// the tile under it instead of dragging.
+let press = null;
+const CLICK_SLOP = 4;
+function twist() {
+  let [a, b] = pointers.values();
+  return Math.atan2(b.y - a.y, b.x - a.x);
+}
+function spread() {
+  let [a, b] = pointers.values();
+  return Math.hypot(b.x - a.x, b.y - a.y);
+}
+view.addEventListener('pointerdown', e => {
+  pointers.set(e.pointerId, { x: e.clientX, y: e.clientY });
+  press = pointers.size == 1 && e.button == 0 ? { x: e.clientX, y: e.clientY } : null;
+  // Pressing down on its own catches the camera if it is still gliding.
+  if(pointers.size == 1 && e.button == 0 && !e.shiftKey && !e.altKey) {
+    app.update_delta(e.clientX, e.clientY);
+  }
+});
 view.addEventListener('pointermove', e => {
+  if(pointers.has(e.pointerId) && pointers.size == 2) {
+    let before = twist(), apart = spread();
+    pointers.set(e.pointerId, { x: e.clientX, y: e.clientY });
+    // The screen's y axis points down, so its angles run clockwise.
+    app.rotate_by(before - twist());
+    let [a, b] = pointers.values();
+    let rect = view.getBoundingClientRect();
+    if(apart > 0) {
+      app.zoom_at((a.x + b.x) / 2 - rect.left, (a.y + b.y) / 2 - rect.top, spread() / apart);
+    }
+    return;
+  }
   if(e.buttons & 1 != 0) {
   	e.target.setPointerCapture(e.pointerId);
-    app.update_delta(e.clientX, e.clientY);
+    // Shift-dragging turns the view about its centre, and alt-dragging pans
+    // the view of the projected plane instead.
+    if(e.shiftKey) {
+      app.update_rotation_delta(e.clientX, e.clientY);
+    } else if(e.altKey) {
+      app.update_view_delta(e.clientX, e.clientY);
+    } else {
+      app.update_delta(e.clientX, e.clientY);
+    }
+  } else if(e.buttons & 2) {
+    // Right-dragging orbits the 3D view of the hyperboloid.
+    e.target.setPointerCapture(e.pointerId);
+    app.update_orbit_delta(e.clientX, e.clientY);
   }
+  app.hover(e.offsetX, e.offsetY);
+});
+view.addEventListener('pointerleave', e => app.clear_hover());
+// Double-clicking a point flies there along the geodesic.
+view.addEventListener('dblclick', e => app.recentre_at(e.offsetX, e.offsetY, 600));
+view.addEventListener('contextmenu', e => e.preventDefault());
+for(let type of ['pointerup', 'pointercancel']) {
+  view.addEventListener(type, e => {
+    pointers.delete(e.pointerId);
+    app.reset_delta();
+    if(type == 'pointerup' && press
+       && Math.hypot(e.clientX - press.x, e.clientY - press.y) < CLICK_SLOP) {
+      app.toggle_selection(e.offsetX, e.offsetY);
+    }
+    press = null;
+  });
+}
+// Held down, the arrow keys and WASD move through the plane, Q and E turn
+// the view counterclockwise and clockwise, and + and - zoom it.
+addEventListener('keydown', e => {
+  if(e.target instanceof HTMLInputElement || e.target instanceof HTMLTextAreaElement
+     || e.ctrlKey || e.metaKey || e.altKey) {
+    return;
+  }
+  if(app.key_down(e.key)) {
+    e.preventDefault();
+  }
+});
+addEventListener('keyup', e => app.key_up(e.key));
+addEventListener('blur', e => app.release_keys());
+// The left stick of a gamepad moves, and the right one turns and zooms.
+// Gamepads have no events for their sticks, so they are read every frame
+// while any is connected.
+let polling = false;
+function pollGamepads() {
+  let pad = [...navigator.getGamepads()].find(pad => pad);
+  polling = !!pad;
+  if(!pad) {
+    return;
+  }
+  let [x = 0, y = 0, turn = 0, zoom = 0] = pad.axes;
+  // The sticks' y axes point down.
+  app.set_gamepad(x, -y, -turn, -zoom);
+  requestAnimationFrame(pollGamepads);
+}
+addEventListener('gamepadconnected', e => {
+  if(!polling) {
+    polling = true;
+    requestAnimationFrame(pollGamepads);
+  }
+});
+addEventListener('gamepaddisconnected', e => app.set_gamepad(0, 0, 0, 0));
+view.addEventListener('wheel', e => {
+  e.preventDefault();
+  app.zoom_at(e.offsetX, e.offsetY, Math.exp(-e.deltaY / 500));
 });
-view.addEventListener('pointerup', e => app.reset_delta());
 
 for(let p of document.getElementsByClassName('projection')) {
   p.addEventListener('input', e => {
-    app.set_projection(e.target.value);
+    app.animate_projection(e.target.value, 1000);
+  });
+}
+// Drawn per pixel, the tiles cannot be told apart to be highlighted, unless
+// the tiling has no domain to fold into and its mesh is drawn instead.
+function showSelectable() {
+  let selectable = app.can_select();
+  clearSelection.disabled = !selectable;
+  selectionNote.hidden = selectable;
+}
+for(let r of document.getElementsByClassName('renderer')) {
+  r.addEventListener('input', e => {
+    app.set_renderer(e.target.value);
+    showSelectable();
   });
 }
 depth.addEventListener('input', e => app.set_depth(Number(e.target.value)));
+overlay.addEventListener('input', e => app.set_overlay(e.target.checked));
+models.addEventListener('input', e => app.set_models(e.target.checked));
+clearSelection.addEventListener('click', e => app.clear_selection());
+// Tiles less than half a pixel across are left out of the exported image.
+exportSvg.addEventListener('click', e => {
+  let svg;
+  try {
+    svg = app.export_svg(0.5);
+  } catch(err) {
+    alert(err.message);
+    return;
+  }
+  let link = document.createElement('a');
+  link.href = URL.createObjectURL(new Blob([svg], { type: 'image/svg+xml' }));
+  link.download = 'tiling.svg';
+  link.click();
+  URL.revokeObjectURL(link.href);
+});
 
 schlafliP.addEventListener('input', e => {
   schlafliQ.min = Math.floor(3 + 4 / (+e.target.value - 2));
   schlafliQ.max = +schlafliQ.min + 10;
 });
+// Infinity is passed to the generator as undefined.
+function schlafli() {
+  return [
+    schlafliPInfinite.checked ? undefined : +schlafliP.value,
+    schlafliQInfinite.checked ? undefined : +schlafliQ.value,
+  ];
+}
+for(let [order, infinite] of [[schlafliP, schlafliPInfinite], [schlafliQ, schlafliQInfinite]]) {
+  infinite.addEventListener('input', e => order.disabled = e.target.checked);
+}
+function updateTiling(create) {
+  try {
+    tilingGenerator = create();
+  } catch(err) {
+    tilingError.textContent = err.message;
+    return;
+  }
+  tilingError.textContent = '';
+  app.set_tiling(tilingGenerator, Number(depth.value));
+  showSelectable();
+}
 submitTiling.addEventListener('click', e => {
   e.preventDefault();
-  tilingGenerator = new Tiling(+schlafliP.value, +schlafliQ.value, tiling.value);
+  updateTiling(() => new Tiling(...schlafli(), tiling.value));
+});
+generateTiling.addEventListener('click', e => {
+  e.preventDefault();
+  updateTiling(() => {
+    let generator = Tiling.from_schlafli(...schlafli());
+    tiling.value = generator.table();
+    return generator;
+  });
+});
+submitWythoff.addEventListener('click', e => {
+  e.preventDefault();
+  let generator;
+  try {
+    generator = Tiling.from_wythoff(wythoff.value);
+  } catch(err) {
+    wythoffError.textContent = err.message;
+    return;
+  }
+  wythoffError.textContent = '';
+  tilingGenerator = generator;
   app.set_tiling(tilingGenerator, Number(depth.value));
+  showSelectable();
+});
+submitTriangle.addEventListener('click', e => {
+  e.preventDefault();
+  let triangle;
+  try {
+    triangle = new TriangleTiling(+triangleP.value, +triangleQ.value, +triangleR.value);
+  } catch(err) {
+    triangleError.textContent = err.message;
+    return;
+  }
+  triangleError.textContent = '';
+  app.set_triangle_tiling(triangle, Number(depth.value));
+  showSelectable();
 });
 
 async function run() {
   await init();
-  tilingGenerator = new Tiling(+schlafliP.value, +schlafliQ.value, tiling.value);
+  tilingGenerator = new Tiling(...schlafli(), tiling.value);
   let window = new AppWindow(document.getElementById('view'), () => requestAnimationFrame(() => app.draw()));
   app = await new App(tilingGenerator, window);
   app.set_depth(Number(depth.value));
+  app.set_overlay(overlay.checked);
+  app.set_models(models.checked);
 }
 run();